@@ -0,0 +1,365 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use super::{Context, JsonError};
+use std::iter;
+use std::str;
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq)]
+pub enum Token {
+    ArrayStart,
+    ArrayEnd,
+    ObjectStart,
+    ObjectEnd,
+    SeparatorName,
+    SeparatorValue,
+    ValueNull,
+    ValueNumber(f64),
+    ValueBoolean(bool),
+    ValueString(String),
+}
+
+pub type LexerResult = Result<TokenInfo, JsonError>;
+
+#[derive(Debug)]
+pub struct TokenInfo {
+    pub token: Token,
+    pub context: Context,
+}
+
+pub struct Lexer<'a> {
+    char_context: Context,
+    token_context: Context,
+    data_iter: iter::Peekable<str::Chars<'a>>,
+}
+
+fn string_to_unicode_char(number: &str) -> Option<char> {
+    u32::from_str_radix(number, 16)
+        .ok()
+        .and_then(std::char::from_u32)
+}
+
+fn is_high_surrogate(number: &str) -> bool {
+    assert!(number.len() == 4);
+    match u16::from_str_radix(number, 16) {
+        Ok(high) => (0xD800..=0xDBFF).contains(&high),
+        Err(_) => false,
+    }
+}
+
+fn convert_surrogate_pairs(high: &str, low: &str) -> Option<char> {
+    assert!(high.len() == 4);
+    assert!(low.len() == 4);
+    let h = u32::from_str_radix(high, 16).ok()?;
+    let l = u32::from_str_radix(low, 16).ok()?;
+    std::char::from_u32((h - 0xD800) * 0x400 + l - 0xDC00 + 0x10000)
+}
+
+impl std::iter::Iterator for Lexer<'_> {
+    type Item = LexerResult;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let c = self.trim_whitespace_and_peek()?;
+        self.set_token_context();
+        let result = match c {
+            'f' => {
+                self.consume_seq_and_emit(&['f', 'a', 'l', 's', 'e'], Token::ValueBoolean(false))
+            }
+            't' => self.consume_seq_and_emit(&['t', 'r', 'u', 'e'], Token::ValueBoolean(true)),
+            'n' => self.consume_seq_and_emit(&['n', 'u', 'l', 'l'], Token::ValueNull),
+            ':' => self.consume_next_and_emit(Token::SeparatorName),
+            ',' => self.consume_next_and_emit(Token::SeparatorValue),
+            '{' => self.consume_next_and_emit(Token::ObjectStart),
+            '}' => self.consume_next_and_emit(Token::ObjectEnd),
+            '[' => self.consume_next_and_emit(Token::ArrayStart),
+            ']' => self.consume_next_and_emit(Token::ArrayEnd),
+            '"' => self.consume_string(),
+            '-' | '0'..='9' => self.consume_number(),
+            c => Err(self.build_error(format!("The character '{}' is unexpected", c))),
+        };
+        Some(result)
+    }
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(data: &'a str) -> Lexer<'a> {
+        Lexer {
+            char_context: Default::default(),
+            token_context: Default::default(),
+            data_iter: data.chars().peekable(),
+        }
+    }
+
+    fn build_result(&self, token: Token) -> TokenInfo {
+        let context = self.token_context.clone();
+        TokenInfo { context, token }
+    }
+
+    fn build_error(&self, message: String) -> JsonError {
+        let context = self.char_context.clone();
+        JsonError::Lexer { context, message }
+    }
+
+    fn set_token_context(&mut self) {
+        self.token_context = self.char_context.clone();
+    }
+
+    fn peek_char(&mut self) -> Option<&char> {
+        self.data_iter.peek()
+    }
+
+    fn trim_whitespace_and_peek(&mut self) -> Option<char> {
+        loop {
+            match self.peek_char()? {
+                ' ' | '\t' | '\r' | '\n' => self.consume_char(),
+                &candidate => return Some(candidate),
+            };
+        }
+    }
+
+    fn consume_char(&mut self) -> Option<char> {
+        let next_value = self.data_iter.next();
+        if let Some(c) = next_value {
+            match c {
+                '\n' => {
+                    self.char_context.column = 0;
+                    self.char_context.line += 1;
+                }
+                _ => self.char_context.column += 1,
+            }
+        }
+        next_value
+    }
+
+    fn consume_n_times(&mut self, n: usize) -> Result<String, JsonError> {
+        let mut result = String::new();
+        for _ in 0..n {
+            let c = self.consume_char().ok_or_else(|| {
+                self.build_error(String::from(
+                    "End of stream while waiting for more characters",
+                ))
+            })?;
+            result.push(c);
+        }
+        Ok(result)
+    }
+
+    fn consume_next_and_emit(&mut self, token: Token) -> LexerResult {
+        match self.consume_char() {
+            None => Err(self.build_error(String::from("No more data to read."))),
+            Some(_) => Ok(self.build_result(token)),
+        }
+    }
+
+    fn consume_seq(&mut self, pattern: &[char]) -> Result<(), JsonError> {
+        for &target_char in pattern.iter() {
+            let candidate_char = self.consume_char().ok_or_else(|| {
+                self.build_error(format!("End of stream while waiting for '{}'", target_char))
+            })?;
+            if candidate_char != target_char {
+                return Err(self.build_error(format!(
+                    "Unexpected char '{}', was waiting for a '{}'",
+                    candidate_char, target_char
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn consume_seq_and_emit(&mut self, pattern: &[char], token: Token) -> LexerResult {
+        self.consume_seq(pattern)?;
+        Ok(self.build_result(token))
+    }
+
+    fn consume_string(&mut self) -> LexerResult {
+        match self.consume_char() {
+            Some('"') => (),
+            _ => panic!("Logic error, next char should have been a '\"'"),
+        }
+        let mut result = String::new();
+        let mut is_escaping = false;
+        loop {
+            let c = self.consume_char().ok_or_else(|| {
+                self.build_error(String::from("EOF encountered while recognizing a string"))
+            })?;
+            if is_escaping {
+                let transcoded_char =
+                    match c {
+                        '"' => '\u{0022}',
+                        '\\' => '\u{005C}',
+                        '/' => '\u{002F}',
+                        'b' => '\u{0008}',
+                        'f' => '\u{000C}',
+                        'n' => '\u{000A}',
+                        'r' => '\u{000D}',
+                        't' => '\u{0009}',
+                        'u' => {
+                            let unicode_char = self.consume_n_times(4)?;
+                            if is_high_surrogate(&unicode_char) {
+                                let high_surrogate = unicode_char;
+                                self.consume_seq(&['\\', 'u'])?;
+                                let low_surrogate = self.consume_n_times(4)?;
+                                convert_surrogate_pairs(&high_surrogate, &low_surrogate)
+                                    .ok_or_else(|| {
+                                        self.build_error(String::from(
+                                            "Issue while parsing provided unicode value.",
+                                        ))
+                                    })?
+                            } else {
+                                string_to_unicode_char(unicode_char.as_str()).ok_or_else(|| {
+                                    self.build_error(format!(
+                                        "Could not convert {} to unicode",
+                                        unicode_char
+                                    ))
+                                })?
+                            }
+                        }
+                        rest => {
+                            return Err(self
+                                .build_error(format!("'{} is not an escapable character'", rest)))
+                        }
+                    };
+                result.push(transcoded_char);
+                is_escaping = false;
+                continue;
+            }
+
+            match c {
+                '"' => return Ok(self.build_result(Token::ValueString(result))),
+                '\x20' | '\x21' | '\x23'..='\x5B' | '\x5D'..='\u{10FFFF}' => result.push(c),
+                '\\' => is_escaping = true,
+                _ => return Err(self.build_error(String::from("Not a valid character code"))),
+            };
+        }
+    }
+
+    fn consume_number(&mut self) -> LexerResult {
+        enum Step {
+            Minus,
+            IntFirst,
+            Int,
+            FracOrExp,
+            FracFirst,
+            Frac,
+            ExpSign,
+            ExpFirst,
+            Exp,
+        }
+        let mut step = Step::Minus;
+        let mut number = String::new();
+        'outer: loop {
+            let &c = match self.peek_char() {
+                None => break 'outer,
+                Some(val) => val,
+            };
+            match step {
+                Step::Minus => {
+                    match c {
+                        '-' => {
+                            number.push(c);
+                            self.consume_char();
+                        }
+                        '0'..='9' => (),
+                        _ => panic!("Logic error, next char should have been a '-' or a number"),
+                    };
+                    step = Step::IntFirst;
+                }
+                Step::IntFirst => {
+                    match c {
+                        '0' => step = Step::FracOrExp,
+                        '1'..='9' => step = Step::Int,
+                        _ => break 'outer,
+                    }
+                    number.push(c);
+                    self.consume_char();
+                }
+                Step::Int => {
+                    match c {
+                        '.' => step = Step::FracFirst,
+                        'e' | 'E' => step = Step::ExpSign,
+                        '0'..='9' => (),
+                        _ => break 'outer,
+                    }
+                    number.push(c);
+                    self.consume_char();
+                }
+                Step::FracOrExp => {
+                    match c {
+                        '.' => step = Step::FracFirst,
+                        'e' | 'E' => step = Step::ExpSign,
+                        _ => break 'outer,
+                    }
+                    number.push(c);
+                    self.consume_char();
+                }
+                Step::FracFirst => {
+                    match c {
+                        '0'..='9' => step = Step::Frac,
+                        _ => break 'outer,
+                    }
+                    number.push(c);
+                    self.consume_char();
+                }
+                Step::Frac => {
+                    match c {
+                        'e' | 'E' => step = Step::ExpSign,
+                        '0'..='9' => (),
+                        _ => break 'outer,
+                    }
+                    number.push(c);
+                    self.consume_char();
+                }
+                Step::ExpSign => {
+                    match c {
+                        '+' | '-' => {
+                            number.push(c);
+                            self.consume_char();
+                        }
+                        '0'..='9' => (),
+                        _ => break 'outer,
+                    }
+                    step = Step::ExpFirst
+                }
+                Step::ExpFirst => {
+                    match c {
+                        '0'..='9' => step = Step::Exp,
+                        _ => break 'outer,
+                    }
+                    number.push(c);
+                    self.consume_char();
+                }
+                Step::Exp => {
+                    match c {
+                        '0'..='9' => (),
+                        _ => break 'outer,
+                    }
+                    number.push(c);
+                    self.consume_char();
+                }
+            }
+        }
+        f64::from_str(number.as_str())
+            .map(|val| self.build_result(Token::ValueNumber(val)))
+            .map_err(|_| self.build_error(format!("Could not convert '{}' to a number", number)))
+    }
+}
@@ -0,0 +1,170 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use super::lexer::{Lexer, Token, TokenInfo};
+use super::JsonError;
+use std::collections::HashMap;
+
+// TODO Should I reimplement PartialEq to allow for float comparison?
+#[derive(Debug, PartialEq)]
+pub enum Json {
+    Object(HashMap<String, Json>),
+    Array(Vec<Json>),
+    String(String),
+    Number(f64),
+    Boolean(bool),
+    Null,
+}
+
+pub fn parse_json(input: &str) -> Result<Json, JsonError> {
+    let lexer = Lexer::new(input);
+    let mut parser = Parser::new(lexer)?;
+    parser.parse()
+}
+
+pub struct Parser<'a> {
+    pub lexer: Lexer<'a>,
+    pub current_token_info: TokenInfo,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(mut lexer: Lexer<'a>) -> Result<Self, JsonError> {
+        let token_info_result = lexer
+            .next()
+            .ok_or_else(|| JsonError::Other(String::from("No data to parse")))?;
+        let current_token_info = token_info_result?;
+        Ok(Parser {
+            lexer,
+            current_token_info,
+        })
+    }
+
+    pub fn parse(&mut self) -> Result<Json, JsonError> {
+        self.parse_json_value()
+    }
+
+    fn build_parser_error(&self, message: String) -> JsonError {
+        JsonError::Parser {
+            message,
+            context: self.current_token_info.context.clone(),
+        }
+    }
+
+    fn advance(&mut self) -> Result<(), JsonError> {
+        let token_info_result = self
+            .lexer
+            .next()
+            .ok_or_else(|| JsonError::Other(String::from("No data to parse")))?;
+        self.current_token_info = token_info_result?;
+        Ok(())
+    }
+
+    fn advance_and_validate(&mut self, token: Token) -> Result<(), JsonError> {
+        let token_result = self
+            .lexer
+            .next()
+            .ok_or_else(|| JsonError::Other(String::from("No data to parse")))??
+            .token;
+        if token_result == token {
+            Ok(())
+        } else {
+            Err(self.build_parser_error(format!(
+                "Was waiting {:?} but received {:?}",
+                token, token_result
+            )))
+        }
+    }
+
+    fn parse_json_value(&mut self) -> Result<Json, JsonError> {
+        let result = match &self.current_token_info.token {
+            Token::ArrayStart => Json::Array(self.parse_array()?),
+            Token::ObjectStart => Json::Object(self.parse_object()?),
+            Token::ValueNull => Json::Null,
+            Token::ValueNumber(n) => Json::Number(*n),
+            Token::ValueBoolean(b) => Json::Boolean(*b),
+            Token::ValueString(s) => Json::String(s.to_string()),
+            other => return Err(self.build_parser_error(format!("The token '{:?}' is not valid here, was waiting the start of an array, object or a value", other))),
+        };
+        Ok(result)
+    }
+
+    fn parse_array(&mut self) -> Result<Vec<Json>, JsonError> {
+        assert_eq!(self.current_token_info.token, Token::ArrayStart);
+        let mut vec = Vec::new();
+        self.advance()?;
+        if let Token::ArrayEnd = self.current_token_info.token {
+            return Ok(vec);
+        }
+        loop {
+            let value = self.parse_json_value()?;
+            vec.push(value);
+            self.advance()?;
+            match &self.current_token_info.token {
+                Token::ArrayEnd => return Ok(vec),
+                Token::SeparatorValue => {}
+                other => {
+                    return Err(self.build_parser_error(format!(
+                        "Was waiting a ',' or ']' but received {:?}",
+                        other
+                    )))
+                }
+            }
+            self.advance()?;
+        }
+    }
+
+    fn parse_object(&mut self) -> Result<HashMap<String, Json>, JsonError> {
+        assert_eq!(self.current_token_info.token, Token::ObjectStart);
+        let mut map = HashMap::new();
+        self.advance()?;
+        if let Token::ObjectEnd = self.current_token_info.token {
+            return Ok(map);
+        }
+        loop {
+            let key = match &self.current_token_info.token {
+                Token::ValueString(val) => val.clone(),
+                other => {
+                    return Err(self.build_parser_error(format!(
+                        "Was waiting a string but received {:?}",
+                        other
+                    )))
+                }
+            };
+            self.advance_and_validate(Token::SeparatorName)?;
+            self.advance()?;
+            let value = self.parse_json_value()?;
+            map.insert(key, value);
+            self.advance()?;
+            match &self.current_token_info.token {
+                Token::ObjectEnd => return Ok(map),
+                Token::SeparatorValue => {}
+                other => {
+                    return Err(self.build_parser_error(format!(
+                        "Was waiting a ',' or '}}' but received {:?}",
+                        other
+                    )))
+                }
+            }
+            self.advance()?;
+        }
+    }
+}
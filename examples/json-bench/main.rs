@@ -0,0 +1,115 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+mod baseline;
+
+use clap::Parser;
+use json_parser::{parse_json, validate, Lexer, Parser as JsonParser};
+use std::fs;
+use std::time::{Duration, Instant};
+
+/// Measure lexing and parsing throughput on JSON files, for instance
+/// twitter.json, citm_catalog.json and canada.json from the json-benchmark suite,
+/// against the lexer and parser of the first version of the crate.
+#[derive(Parser)]
+struct Opts {
+    #[clap(short, long, default_value = "20")]
+    iterations: u32,
+    files: Vec<String>,
+}
+
+fn main() {
+    let opts: Opts = Opts::parse();
+    if opts.files.is_empty() {
+        println!("Please add at least one file");
+        return;
+    }
+    for file in opts.files.iter() {
+        let data = fs::read_to_string(file).expect("Something went wrong reading the file");
+        let baseline_lex_time = measure(opts.iterations, || {
+            for token in baseline::Lexer::new(data.as_str()) {
+                token.expect("The file could not be lexed");
+            }
+        });
+        let baseline_parse_time = measure(opts.iterations, || {
+            baseline::parse_json(data.as_str()).expect("The file could not be parsed");
+        });
+        let lex_time = measure(opts.iterations, || {
+            for token in Lexer::new(data.as_str()) {
+                token.expect("The file could not be lexed");
+            }
+        });
+        let parse_time = measure(opts.iterations, || {
+            parse_json(data.as_str()).expect("The file could not be parsed");
+        });
+        let indexed_parse_time = measure(opts.iterations, || {
+            let lexer = Lexer::with_structural_index(data.as_str()).expect("The file is too big");
+            JsonParser::new(lexer)
+                .and_then(|mut parser| parser.parse())
+                .expect("The file could not be parsed");
+        });
+        let validate_time = measure(opts.iterations, || {
+            validate(data.as_str()).expect("The file is not valid");
+        });
+        println!("{}", file);
+        print_result("baseline lexer", data.len(), baseline_lex_time, None);
+        print_result("baseline parser", data.len(), baseline_parse_time, None);
+        print_result("lexer", data.len(), lex_time, Some(baseline_lex_time));
+        print_result("parser", data.len(), parse_time, Some(baseline_parse_time));
+        print_result(
+            "indexed",
+            data.len(),
+            indexed_parse_time,
+            Some(baseline_parse_time),
+        );
+        print_result(
+            "validate",
+            data.len(),
+            validate_time,
+            Some(baseline_parse_time),
+        );
+    }
+}
+
+fn measure<F: FnMut()>(iterations: u32, mut f: F) -> Duration {
+    f();
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    start.elapsed() / iterations
+}
+
+/// Print the duration and throughput, and the speedup against `baseline`.
+fn print_result(name: &str, size: usize, duration: Duration, baseline: Option<Duration>) {
+    let throughput = size as f64 / duration.as_secs_f64() / 1_000_000.;
+    let speedup = baseline
+        .map(|baseline| format!("{:>8.2}x", baseline.as_secs_f64() / duration.as_secs_f64()))
+        .unwrap_or_default();
+    println!(
+        "  {:<16}{:>10.3} ms{:>10.1} MB/s{}",
+        name,
+        duration.as_secs_f64() * 1000.,
+        throughput,
+        speedup
+    );
+}
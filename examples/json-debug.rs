@@ -20,35 +20,40 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
 
-use clap::Clap;
-use json_parser::parse_json;
+use clap::Parser;
+use json_parser::{parse_json, render_diagnostic, DiagnosticOptions};
 use std::fs;
 
-#[derive(Clap)]
+#[derive(Parser)]
 struct Opts {
     #[clap(short, long)]
     string: Option<String>,
     #[clap(short, long)]
     file: Option<String>,
+    #[clap(short, long)]
+    color: bool,
 }
 
 fn main() {
     let opts: Opts = Opts::parse();
     if opts.file.is_some() && opts.string.is_some() {
         println!("Please select only one option");
-    } else if let Some(data) = opts.string {
-        start_parsing(data.as_str());
-    } else if let Some(file) = opts.file {
+    } else if let Some(data) = &opts.string {
+        start_parsing(data.as_str(), None, opts.color);
+    } else if let Some(file) = &opts.file {
         let data = fs::read_to_string(file).expect("Something went wrong reading the file");
-        start_parsing(data.as_str());
+        start_parsing(data.as_str(), Some(file.as_str()), opts.color);
     } else {
         println!("Please add an option");
     }
 }
 
-fn start_parsing(data: &str) {
+fn start_parsing(data: &str, file_name: Option<&str>, color: bool) {
     match parse_json(data) {
         Ok(json) => println!("{:?}", json),
-        Err(error) => println!("{}", error),
+        Err(error) => {
+            let options = DiagnosticOptions { file_name, color };
+            print!("{}", render_diagnostic(&error, data, &options))
+        }
     }
 }
@@ -0,0 +1,772 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Lossless concrete syntax tree, to edit JSON documents without reformatting
+//! them.
+//!
+//! Every token keeps its raw text, and the whitespace and comments before it
+//! as trivia, so printing the tree gives back the input byte for byte.
+//! Replacing a value only changes the text of that value.
+
+use crate::grammar::Builder;
+use crate::lexer::{Lexer, TokenInfo};
+use crate::parser::{JsonBuilder, Parser};
+use crate::{Dialect, Json, JsonError, ParseLimits, SurrogatePolicy};
+use std::collections::HashMap;
+use std::fmt;
+use std::slice;
+
+/// A token with its raw text and the whitespace, and comments, preceding it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CstToken {
+    leading_trivia: String,
+    text: String,
+}
+
+impl CstToken {
+    pub fn leading_trivia(&self) -> &str {
+        &self.leading_trivia
+    }
+
+    /// The comments in the leading trivia, with their delimiters.
+    pub fn comments(&self) -> Vec<&str> {
+        trivia_comments(&self.leading_trivia)
+    }
+
+    /// The token as written in the source, with its escapes for strings.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl fmt::Display for CstToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.leading_trivia)?;
+        f.write_str(&self.text)
+    }
+}
+
+/// A string, number, boolean or null, with its raw token and its value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CstScalar {
+    token: CstToken,
+    value: Json,
+}
+
+impl CstScalar {
+    pub fn token(&self) -> &CstToken {
+        &self.token
+    }
+
+    pub fn value(&self) -> &Json {
+        &self.value
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CstMember {
+    key_token: CstToken,
+    key: String,
+    colon: CstToken,
+    value: CstValue,
+    comma: Option<CstToken>,
+}
+
+impl CstMember {
+    /// The unescaped key.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn key_token(&self) -> &CstToken {
+        &self.key_token
+    }
+
+    pub fn value(&self) -> &CstValue {
+        &self.value
+    }
+
+    pub fn value_mut(&mut self) -> &mut CstValue {
+        &mut self.value
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CstObject {
+    open: CstToken,
+    members: Vec<CstMember>,
+    close: CstToken,
+}
+
+impl CstObject {
+    pub fn members(&self) -> &[CstMember] {
+        &self.members
+    }
+
+    pub fn members_mut(&mut self) -> &mut [CstMember] {
+        &mut self.members
+    }
+
+    /// The value of the last member with this key, as `Json` would keep it.
+    pub fn get(&self, key: &str) -> Option<&CstValue> {
+        self.members
+            .iter()
+            .rev()
+            .find(|member| member.key == key)
+            .map(|member| &member.value)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut CstValue> {
+        self.members
+            .iter_mut()
+            .rev()
+            .find(|member| member.key == key)
+            .map(|member| &mut member.value)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CstElement {
+    value: CstValue,
+    comma: Option<CstToken>,
+}
+
+impl CstElement {
+    pub fn value(&self) -> &CstValue {
+        &self.value
+    }
+
+    pub fn value_mut(&mut self) -> &mut CstValue {
+        &mut self.value
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CstArray {
+    open: CstToken,
+    elements: Vec<CstElement>,
+    close: CstToken,
+}
+
+impl CstArray {
+    pub fn elements(&self) -> &[CstElement] {
+        &self.elements
+    }
+
+    pub fn elements_mut(&mut self) -> &mut [CstElement] {
+        &mut self.elements
+    }
+
+    pub fn get(&self, index: usize) -> Option<&CstValue> {
+        self.elements.get(index).map(|element| &element.value)
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut CstValue> {
+        self.elements
+            .get_mut(index)
+            .map(|element| &mut element.value)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CstValue {
+    Object(CstObject),
+    Array(CstArray),
+    Scalar(CstScalar),
+}
+
+impl CstValue {
+    pub fn as_object(&self) -> Option<&CstObject> {
+        match self {
+            CstValue::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut CstObject> {
+        match self {
+            CstValue::Object(object) => Some(object),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&CstArray> {
+        match self {
+            CstValue::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut CstArray> {
+        match self {
+            CstValue::Array(array) => Some(array),
+            _ => None,
+        }
+    }
+
+    pub fn as_scalar(&self) -> Option<&CstScalar> {
+        match self {
+            CstValue::Scalar(scalar) => Some(scalar),
+            _ => None,
+        }
+    }
+
+    /// Follow a path of object keys and array indexes.
+    pub fn pointer_mut(&mut self, path: &[&str]) -> Option<&mut CstValue> {
+        let mut current = self;
+        for step in path {
+            current = match current {
+                CstValue::Object(object) => object.get_mut(step)?,
+                CstValue::Array(array) => array.get_mut(step.parse().ok()?)?,
+                CstValue::Scalar(_) => return None,
+            };
+        }
+        Some(current)
+    }
+
+    /// The whitespace before the first token of the value.
+    pub fn leading_trivia(&self) -> &str {
+        match self {
+            CstValue::Object(object) => &object.open.leading_trivia,
+            CstValue::Array(array) => &array.open.leading_trivia,
+            CstValue::Scalar(scalar) => &scalar.token.leading_trivia,
+        }
+    }
+
+    fn leading_trivia_mut(&mut self) -> &mut String {
+        match self {
+            CstValue::Object(object) => &mut object.open.leading_trivia,
+            CstValue::Array(array) => &mut array.open.leading_trivia,
+            CstValue::Scalar(scalar) => &mut scalar.token.leading_trivia,
+        }
+    }
+
+    /// Replace the value with the given JSON text, kept as written.
+    ///
+    /// The whitespace before the value is preserved, so only the bytes of the
+    /// value itself change in the printed document.
+    pub fn replace_with_text(&mut self, text: &str) -> Result<(), JsonError> {
+        let document = CstDocument::parse(text)?;
+        let mut value = document.root;
+        *value.leading_trivia_mut() = std::mem::take(self.leading_trivia_mut());
+        *self = value;
+        Ok(())
+    }
+
+    /// Replace the value with the compact serialization of `json`.
+    ///
+    /// Fails if `json` exceeds the default limits, see `ParseLimits`.
+    pub fn replace(&mut self, json: &Json) -> Result<(), JsonError> {
+        let text = json.to_string();
+        // Lone surrogates of `Wtf8String` values are written as escapes
+        let document = parse_document(
+            &text,
+            ParseLimits::default(),
+            Dialect::Json,
+            SurrogatePolicy::Preserve,
+        )?;
+        let mut value = document.root;
+        *value.leading_trivia_mut() = std::mem::take(self.leading_trivia_mut());
+        *self = value;
+        Ok(())
+    }
+
+    /// Convert to a `Json` value, dropping the formatting.
+    pub fn to_json(&self) -> Json {
+        // Iterate with a stack, deeply nested values must not overflow
+        enum Frame<'a> {
+            Array(slice::Iter<'a, CstElement>, Vec<Json>),
+            Object(slice::Iter<'a, CstMember>, HashMap<String, Json>, &'a str),
+        }
+        let mut stack = Vec::new();
+        let mut value = self;
+        loop {
+            let mut done = match value {
+                CstValue::Scalar(scalar) => Some(scalar.value.clone()),
+                CstValue::Array(array) => {
+                    stack.push(Frame::Array(array.elements.iter(), Vec::new()));
+                    None
+                }
+                CstValue::Object(object) => {
+                    stack.push(Frame::Object(object.members.iter(), HashMap::new(), ""));
+                    None
+                }
+            };
+            // Give the converted value to its container, and close the
+            // containers which have no value left
+            loop {
+                let closed = match stack.last_mut() {
+                    None => return done.expect("The root is converted"),
+                    Some(Frame::Array(elements, vec)) => {
+                        vec.extend(done.take());
+                        match elements.next() {
+                            Some(element) => {
+                                value = &element.value;
+                                break;
+                            }
+                            None => Json::Array(std::mem::take(vec)),
+                        }
+                    }
+                    Some(Frame::Object(members, map, key)) => {
+                        if let Some(done) = done.take() {
+                            map.insert(key.to_string(), done);
+                        }
+                        match members.next() {
+                            Some(member) => {
+                                *key = &member.key;
+                                value = &member.value;
+                                break;
+                            }
+                            None => Json::Object(std::mem::take(map)),
+                        }
+                    }
+                };
+                stack.pop();
+                done = Some(closed);
+            }
+        }
+    }
+}
+
+/// Drop without recursion, as `Json` does.
+impl Drop for CstValue {
+    fn drop(&mut self) {
+        let mut pending = Vec::new();
+        take_children(self, &mut pending);
+        while let Some(mut value) = pending.pop() {
+            take_children(&mut value, &mut pending);
+        }
+    }
+}
+
+fn take_children(value: &mut CstValue, pending: &mut Vec<CstValue>) {
+    match value {
+        CstValue::Array(array) => {
+            pending.extend(array.elements.drain(..).map(|element| element.value))
+        }
+        CstValue::Object(object) => {
+            pending.extend(object.members.drain(..).map(|member| member.value))
+        }
+        CstValue::Scalar(_) => (),
+    }
+}
+
+impl fmt::Display for CstValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Iterate with a stack, deeply nested values must not overflow
+        enum Item<'a> {
+            Value(&'a CstValue),
+            Token(&'a CstToken),
+        }
+        let mut pending = vec![Item::Value(self)];
+        while let Some(item) = pending.pop() {
+            match item {
+                Item::Token(token) => write!(f, "{}", token)?,
+                Item::Value(CstValue::Scalar(scalar)) => write!(f, "{}", scalar.token)?,
+                Item::Value(CstValue::Array(array)) => {
+                    write!(f, "{}", array.open)?;
+                    pending.push(Item::Token(&array.close));
+                    for element in array.elements.iter().rev() {
+                        pending.extend(element.comma.as_ref().map(Item::Token));
+                        pending.push(Item::Value(&element.value));
+                    }
+                }
+                Item::Value(CstValue::Object(object)) => {
+                    write!(f, "{}", object.open)?;
+                    pending.push(Item::Token(&object.close));
+                    for member in object.members.iter().rev() {
+                        pending.extend(member.comma.as_ref().map(Item::Token));
+                        pending.push(Item::Value(&member.value));
+                        pending.push(Item::Token(&member.colon));
+                        pending.push(Item::Token(&member.key_token));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A whole document: its root value and the whitespace after it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CstDocument {
+    root: CstValue,
+    trailing_trivia: String,
+}
+
+impl CstDocument {
+    /// Parse with the default limits, see `ParseLimits`.
+    pub fn parse(input: &str) -> Result<Self, JsonError> {
+        Self::parse_with_dialect(input, Dialect::Json)
+    }
+
+    /// With the dialects allowing comments, they are kept in the trivia.
+    pub fn parse_with_dialect(input: &str, dialect: Dialect) -> Result<Self, JsonError> {
+        parse_document(
+            input,
+            ParseLimits::default(),
+            dialect,
+            SurrogatePolicy::default(),
+        )
+    }
+
+    pub fn root(&self) -> &CstValue {
+        &self.root
+    }
+
+    pub fn root_mut(&mut self) -> &mut CstValue {
+        &mut self.root
+    }
+
+    pub fn trailing_trivia(&self) -> &str {
+        &self.trailing_trivia
+    }
+}
+
+/// Split the comments out of whitespace and comments.
+fn trivia_comments(mut trivia: &str) -> Vec<&str> {
+    let mut comments = Vec::new();
+    loop {
+        trivia = trivia.trim_start();
+        let end = if trivia.starts_with("//") {
+            trivia.find(['\n', '\r']).unwrap_or(trivia.len())
+        } else if let Some(body) = trivia.strip_prefix("/*") {
+            body.find("*/").map_or(trivia.len(), |idx| idx + 4)
+        } else {
+            return comments;
+        };
+        comments.push(&trivia[..end]);
+        trivia = &trivia[end..];
+    }
+}
+
+impl fmt::Display for CstDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.root, self.trailing_trivia)
+    }
+}
+
+pub(crate) fn parse_document(
+    input: &str,
+    limits: ParseLimits,
+    dialect: Dialect,
+    surrogates: SurrogatePolicy,
+) -> Result<CstDocument, JsonError> {
+    let mut lexer = Lexer::new(input);
+    lexer.set_dialect(dialect);
+    lexer.set_surrogate_policy(surrogates);
+    let mut parser = Parser::with_limits(lexer, limits)?;
+    let root = parser.parse_with(CstBuilder { input, last_end: 0 })?;
+    // The current token is the last one of the value
+    let last_end = parser.current_token_info.end.offset;
+    Ok(CstDocument {
+        root,
+        trailing_trivia: input[last_end..].to_string(),
+    })
+}
+
+/// Builds the tree from the tokens, the trivia being the text between them.
+struct CstBuilder<'a> {
+    input: &'a str,
+    /// End of the last token
+    last_end: usize,
+}
+
+/// Key token, unescaped key and colon of the member being read.
+type PendingKey = (CstToken, String, Option<CstToken>);
+
+impl<'a> CstBuilder<'a> {
+    fn take_token(&mut self, token_info: &TokenInfo) -> CstToken {
+        let (start, end) = (token_info.context.offset, token_info.end.offset);
+        let token = CstToken {
+            leading_trivia: self.input[self.last_end..start].to_string(),
+            text: self.input[start..end].to_string(),
+        };
+        self.last_end = end;
+        token
+    }
+}
+
+impl<'a> Builder for CstBuilder<'a> {
+    type Value = CstValue;
+    type Array = (CstToken, Vec<CstElement>);
+    type Object = (CstToken, Vec<CstMember>, Option<PendingKey>);
+
+    fn scalar(&mut self, token_info: &TokenInfo) -> CstValue {
+        let token = self.take_token(token_info);
+        let value = JsonBuilder.scalar(token_info);
+        CstValue::Scalar(CstScalar { token, value })
+    }
+
+    fn start_array(&mut self, open: &TokenInfo) -> Self::Array {
+        (self.take_token(open), Vec::new())
+    }
+
+    fn push_element(&mut self, array: &mut Self::Array, value: CstValue) {
+        array.1.push(CstElement { value, comma: None });
+    }
+
+    fn array_comma(&mut self, array: &mut Self::Array, comma: &TokenInfo) {
+        let comma = self.take_token(comma);
+        if let Some(element) = array.1.last_mut() {
+            element.comma = Some(comma);
+        }
+    }
+
+    fn end_array(&mut self, array: Self::Array, close: &TokenInfo) -> CstValue {
+        let (open, elements) = array;
+        CstValue::Array(CstArray {
+            open,
+            elements,
+            close: self.take_token(close),
+        })
+    }
+
+    fn start_object(&mut self, open: &TokenInfo) -> Self::Object {
+        (self.take_token(open), Vec::new(), None)
+    }
+
+    fn key(&mut self, object: &mut Self::Object, key: String, token_info: &TokenInfo) {
+        object.2 = Some((self.take_token(token_info), key, None));
+    }
+
+    fn colon(&mut self, object: &mut Self::Object, colon: &TokenInfo) {
+        let colon = self.take_token(colon);
+        if let Some(pending) = &mut object.2 {
+            pending.2 = Some(colon);
+        }
+    }
+
+    fn push_member(&mut self, object: &mut Self::Object, value: CstValue) {
+        if let Some((key_token, key, Some(colon))) = object.2.take() {
+            object.1.push(CstMember {
+                key_token,
+                key,
+                colon,
+                value,
+                comma: None,
+            });
+        }
+    }
+
+    fn object_comma(&mut self, object: &mut Self::Object, comma: &TokenInfo) {
+        let comma = self.take_token(comma);
+        if let Some(member) = object.1.last_mut() {
+            member.comma = Some(comma);
+        }
+    }
+
+    fn end_object(&mut self, object: Self::Object, close: &TokenInfo) -> CstValue {
+        let (open, members, _) = object;
+        CstValue::Object(CstObject {
+            open,
+            members,
+            close: self.take_token(close),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_json, JsonErrorKind, Limit};
+
+    const CONFIG: &str = "{\n  \"name\" : \"demo\",\t\"escaped\": \"caf\\u00e9 \\/ \\\"q\\\"\",\r\n  \"ports\": [ 80,443 , 8080 ],\n  \"ratio\": 1.50E+2,\n  \"nested\": {\"empty\": {}, \"list\": []}\n}\n\n";
+
+    #[test]
+    fn print_is_byte_identical() {
+        let inputs = [
+            CONFIG,
+            "null",
+            "  \"🐱\"  ",
+            "[\n]",
+            "{ \"a\" :\n[ 1 ,\n  -0.0e0 ] }\t",
+        ];
+        for input in inputs.iter() {
+            let document = CstDocument::parse(input).unwrap();
+            assert_eq!(document.to_string(), *input);
+        }
+    }
+
+    #[test]
+    fn raw_text_and_trivia() {
+        let document = CstDocument::parse(CONFIG).unwrap();
+        let object = document.root().as_object().unwrap();
+        let member = &object.members()[1];
+        assert_eq!(member.key(), "escaped");
+        assert_eq!(member.key_token().leading_trivia(), "\t");
+        let scalar = member.value().as_scalar().unwrap();
+        assert_eq!(scalar.token().text(), "\"caf\\u00e9 \\/ \\\"q\\\"\"");
+        assert_eq!(scalar.value(), &Json::String(String::from("café / \"q\"")));
+        let ratio = object.get("ratio").unwrap().as_scalar().unwrap();
+        assert_eq!(ratio.token().text(), "1.50E+2");
+        assert_eq!(document.trailing_trivia(), "\n\n");
+    }
+
+    #[test]
+    fn walk_the_tree() {
+        let document = CstDocument::parse(CONFIG).unwrap();
+        let ports = document.root().as_object().unwrap().get("ports").unwrap();
+        let ports: Vec<&str> = ports
+            .as_array()
+            .unwrap()
+            .elements()
+            .iter()
+            .map(|element| element.value().as_scalar().unwrap().token().text())
+            .collect();
+        assert_eq!(ports, vec!["80", "443", "8080"]);
+        assert_eq!(document.root().to_json(), parse_json(CONFIG).unwrap());
+    }
+
+    #[test]
+    fn replace_only_changes_the_value() {
+        let mut document = CstDocument::parse(CONFIG).unwrap();
+        let port = document.root_mut().pointer_mut(&["ports", "1"]).unwrap();
+        port.replace(&Json::Number(8443.)).unwrap();
+        assert_eq!(document.to_string(), CONFIG.replace("443 ,", "8443 ,"));
+    }
+
+    #[test]
+    fn replace_errors() {
+        let mut document = CstDocument::parse(CONFIG).unwrap();
+        let mut deep = Json::Null;
+        for _ in 0..200 {
+            deep = Json::Array(vec![deep]);
+        }
+        let name = document.root_mut().pointer_mut(&["name"]).unwrap();
+        assert_eq!(
+            name.replace(&deep).unwrap_err().kind,
+            JsonErrorKind::LimitExceeded(Limit::Depth(128))
+        );
+        assert_eq!(document.to_string(), CONFIG);
+        let mut lone = crate::Wtf8String::new();
+        lone.push_surrogate(0xD800);
+        let name = document.root_mut().pointer_mut(&["name"]).unwrap();
+        name.replace(&Json::Wtf8String(lone.clone())).unwrap();
+        assert_eq!(name.to_json(), Json::Wtf8String(lone));
+        assert_eq!(
+            document.to_string(),
+            CONFIG.replace("\"demo\"", "\"\\ud800\"")
+        );
+    }
+
+    #[test]
+    fn deeply_nested_with_unlimited_limits() {
+        let depth = 10_000;
+        let input = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        let document = parse_document(
+            &input,
+            ParseLimits::unlimited(),
+            Dialect::Json,
+            SurrogatePolicy::Strict,
+        )
+        .unwrap();
+        let mut value = document.root();
+        let mut count = 1;
+        while let Some(element) = value.as_array().and_then(|array| array.get(0)) {
+            value = element;
+            count += 1;
+        }
+        assert_eq!(count, depth);
+    }
+
+    #[test]
+    fn deeply_nested_conversions() {
+        let depth = 100_000;
+        let input = format!("{}{}", r#"{"a":["#.repeat(depth), "]}".repeat(depth));
+        let document = parse_document(
+            &input,
+            ParseLimits::unlimited(),
+            Dialect::Json,
+            SurrogatePolicy::Strict,
+        )
+        .unwrap();
+        assert_eq!(document.to_string(), input);
+        let json = document.root().to_json();
+        assert_eq!(json.to_string(), input);
+        json.drop_iteratively();
+    }
+
+    #[test]
+    fn replace_with_text_keeps_the_new_formatting() {
+        let mut document = CstDocument::parse(CONFIG).unwrap();
+        let nested = document.root_mut().pointer_mut(&["nested"]).unwrap();
+        nested.replace_with_text("{ \"on\": true }").unwrap();
+        let target = CONFIG.replace("{\"empty\": {}, \"list\": []}", "{ \"on\": true }");
+        assert_eq!(document.to_string(), target);
+        assert!(document
+            .root_mut()
+            .pointer_mut(&["nested"])
+            .unwrap()
+            .replace_with_text("{ \"on\": }")
+            .is_err());
+    }
+
+    #[test]
+    fn errors_are_the_parser_errors() {
+        for input in [
+            "[1 2]",
+            "{\"a\" 1}",
+            "{\"a\": 1,}",
+            "",
+            "[1, {\"b\": ",
+            "[1] 2",
+            "1.",
+            "01",
+        ]
+        .iter()
+        {
+            assert_eq!(
+                CstDocument::parse(input).err(),
+                parse_json(input).err(),
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn jsonc_comments_are_trivia() {
+        let input = "// Settings\n{\n  /** Port to listen to */\n  \"port\": 80, // default\n  \"hosts\": [\"a\",],\n}\n";
+        let document = CstDocument::parse_with_dialect(input, Dialect::Jsonc).unwrap();
+        assert_eq!(document.to_string(), input);
+        let object = document.root().as_object().unwrap();
+        assert_eq!(
+            object.members()[0].key_token().comments(),
+            vec!["/** Port to listen to */"]
+        );
+        assert_eq!(
+            object.members()[1].key_token().comments(),
+            vec!["// default"]
+        );
+        let hosts = object.get("hosts").unwrap().as_array().unwrap();
+        assert_eq!(hosts.elements().len(), 1);
+        assert_eq!(
+            document.root().to_json(),
+            crate::parse_json_with_dialect(input, Dialect::Jsonc).unwrap()
+        );
+        assert!(CstDocument::parse(input).is_err());
+        for input in ["[1,]", "{\"a\": 1,}", "[:]", "{:}"].iter() {
+            assert_eq!(CstDocument::parse(input).err(), parse_json(input).err());
+        }
+    }
+}
@@ -0,0 +1,409 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Human readable rendering of a `JsonError`, with the line of the source where
+//! it happened, in the spirit of the compiler diagnostics.
+
+use crate::encoding::Transcoded;
+use crate::lexer::{Lexer, TokenKind};
+use crate::{detect_encoding, Encoding, JsonError, JsonErrorKind};
+use std::fmt::Write;
+
+#[derive(Clone, Debug, Default)]
+pub struct DiagnosticOptions<'a> {
+    /// Name displayed with the position, if any
+    pub file_name: Option<&'a str>,
+    /// Use ANSI escape codes for colours
+    pub color: bool,
+}
+
+struct Style {
+    error: &'static str,
+    gutter: &'static str,
+    marker: &'static str,
+    note: &'static str,
+    bold: &'static str,
+    reset: &'static str,
+}
+
+const PLAIN: Style = Style {
+    error: "",
+    gutter: "",
+    marker: "",
+    note: "",
+    bold: "",
+    reset: "",
+};
+
+const ANSI: Style = Style {
+    error: "\x1b[1;31m",
+    gutter: "\x1b[1;34m",
+    marker: "\x1b[1;31m",
+    note: "\x1b[1;36m",
+    bold: "\x1b[1m",
+    reset: "\x1b[0m",
+};
+
+/// Render the error with the source line, a marker under the offending span,
+/// the expected tokens, and a hint when the mistake looks common.
+pub fn render_diagnostic(error: &JsonError, source: &str, options: &DiagnosticOptions) -> String {
+    let style = if options.color { &ANSI } else { &PLAIN };
+    let mut output = String::new();
+    let (line_text, line_offset) = source_line(source, error.context.line);
+    // Characters before the error in its line, the column unit may differ
+    let start = line_text
+        .char_indices()
+        .take_while(|(idx, _)| line_offset + idx < error.context.offset)
+        .count();
+    let width = span_width(error, source);
+    let line_number = error.context.line.to_string();
+    let padding = " ".repeat(line_number.len());
+
+    writeln!(
+        output,
+        "{}error{}{}: {}{}",
+        style.error, style.reset, style.bold, error.kind, style.reset
+    )
+    .unwrap();
+    let location = format!("{}:{}", error.context.line, error.context.column);
+    match options.file_name {
+        Some(file_name) => writeln!(
+            output,
+            "{}{}-->{} {}:{}",
+            padding, style.gutter, style.reset, file_name, location
+        ),
+        None => writeln!(
+            output,
+            "{}{}-->{} {}",
+            padding, style.gutter, style.reset, location
+        ),
+    }
+    .unwrap();
+    writeln!(output, "{} {}|{}", padding, style.gutter, style.reset).unwrap();
+    writeln!(
+        output,
+        "{}{} |{} {}",
+        style.gutter, line_number, style.reset, line_text
+    )
+    .unwrap();
+    // Keep the tabulations so that the marker is aligned with the source
+    let indent: String = line_text
+        .chars()
+        .take(start)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    writeln!(
+        output,
+        "{} {}|{} {}{}{}{}",
+        padding,
+        style.gutter,
+        style.reset,
+        indent,
+        style.marker,
+        "^".repeat(width),
+        style.reset
+    )
+    .unwrap();
+    if let Some(expected) = expected_tokens(&error.kind) {
+        writeln!(
+            output,
+            "{} {}= expected:{} {}",
+            padding,
+            style.note,
+            style.reset,
+            describe_tokens(expected)
+        )
+        .unwrap();
+    }
+    if let Some(hint) = hint(&error.kind) {
+        writeln!(
+            output,
+            "{} {}= hint:{} {}",
+            padding, style.note, style.reset, hint
+        )
+        .unwrap();
+    }
+    output
+}
+
+/// Render an error of `parse_slice`, whose offsets are in the original bytes,
+/// with the input transcoded to UTF-8 when it is UTF-16 or UTF-32.
+pub fn render_diagnostic_slice(
+    error: &JsonError,
+    source: &[u8],
+    options: &DiagnosticOptions,
+) -> String {
+    let encoding = detect_encoding(source);
+    if encoding == Encoding::Utf8 {
+        // Invalid bytes are only found after the error, offsets are kept
+        return render_diagnostic(error, &String::from_utf8_lossy(source), options);
+    }
+    let transcoded = Transcoded::lossy(source, encoding);
+    let mut error = error.clone();
+    error.context.offset = transcoded.text_offset(error.context.offset);
+    render_diagnostic(&error, &transcoded.text, options)
+}
+
+/// The text of a 1-based line, without its line ending, and its byte offset.
+fn source_line(source: &str, line: usize) -> (&str, usize) {
+    let mut offset = 0;
+    for (idx, text) in source.split('\n').enumerate() {
+        if idx + 1 == line {
+            return (text.strip_suffix('\r').unwrap_or(text), offset);
+        }
+        offset += text.len() + 1;
+    }
+    ("", source.len())
+}
+
+/// Width in characters of the span to underline.
+fn span_width(error: &JsonError, source: &str) -> usize {
+    if let JsonErrorKind::UnexpectedToken { .. } | JsonErrorKind::TrailingToken(_) = error.kind {
+        // The error may not come from this source
+        let rest = match source.get(error.context.offset..) {
+            Some(rest) => rest,
+            None => return 1,
+        };
+        // Read the offending token again to know where it ends
+        let mut lexer = Lexer::new(rest);
+        if let Some(Ok(_)) = lexer.next() {
+            let token_text = &rest[..lexer.context().offset];
+            let token_text = token_text.split('\n').next().unwrap_or("");
+            return usize::max(1, token_text.chars().count());
+        }
+    }
+    1
+}
+
+fn expected_tokens(kind: &JsonErrorKind) -> Option<&[TokenKind]> {
+    match kind {
+        JsonErrorKind::UnexpectedToken { expected, .. }
+        | JsonErrorKind::UnexpectedEof { expected } => {
+            if expected.is_empty() {
+                None
+            } else {
+                Some(expected)
+            }
+        }
+        _ => None,
+    }
+}
+
+fn describe_tokens(tokens: &[TokenKind]) -> String {
+    if tokens == TokenKind::VALUE_START {
+        return String::from("a value");
+    }
+    let descriptions: Vec<String> = tokens.iter().map(|t| t.to_string()).collect();
+    match descriptions.split_last() {
+        Some((last, [])) => last.clone(),
+        Some((last, rest)) => format!("{} or {}", rest.join(", "), last),
+        None => String::new(),
+    }
+}
+
+fn hint(kind: &JsonErrorKind) -> Option<&'static str> {
+    match kind {
+        JsonErrorKind::UnexpectedToken { found, expected } => {
+            let is_closing = *found == TokenKind::ArrayEnd || *found == TokenKind::ObjectEnd;
+            if is_closing
+                && (expected == TokenKind::VALUE_START || expected == &[TokenKind::ValueString])
+            {
+                Some("trailing comma is not allowed")
+            } else if TokenKind::VALUE_START.contains(found)
+                && expected.contains(&TokenKind::SeparatorValue)
+            {
+                Some("missing comma?")
+            } else if expected == &[TokenKind::SeparatorName] {
+                Some("missing ':' after the key?")
+            } else if expected.first() == Some(&TokenKind::ValueString) {
+                Some("object keys must be strings in double quotes")
+            } else {
+                None
+            }
+        }
+        JsonErrorKind::UnexpectedEof { expected } => {
+            if expected.contains(&TokenKind::ArrayEnd) {
+                Some("unclosed array?")
+            } else if expected.contains(&TokenKind::ObjectEnd) {
+                Some("unclosed object?")
+            } else {
+                None
+            }
+        }
+        JsonErrorKind::UnexpectedCharacter { found: '\'', .. } => {
+            Some("strings must be enclosed in double quotes")
+        }
+        JsonErrorKind::UnexpectedCharacter { found: '/', .. } => {
+            Some("comments are not allowed in JSON")
+        }
+        JsonErrorKind::UnterminatedString => Some("missing closing quote?"),
+        JsonErrorKind::TrailingToken(_) => Some("a document holds a single value"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    fn render(source: &str, options: &DiagnosticOptions) -> String {
+        let error = parse_json(source).expect_err("Was waiting an error");
+        render_diagnostic(&error, source, options)
+    }
+
+    fn render_plain(source: &str) -> String {
+        let options = DiagnosticOptions {
+            file_name: Some("config.json"),
+            color: false,
+        };
+        render(source, &options)
+    }
+
+    #[test]
+    fn snapshot_missing_comma() {
+        let source = "{\n  \"a\": 1\n  \"b\": 2\n}";
+        let expected = r#"error: Was waiting ',' or '}' but received a string
+ --> config.json:3:3
+  |
+3 |   "b": 2
+  |   ^^^
+  = expected: ',' or '}'
+  = hint: missing comma?
+"#;
+        assert_eq!(render_plain(source), expected);
+    }
+
+    #[test]
+    fn snapshot_trailing_comma() {
+        let source = "[1, 2, ]";
+        let expected = r#"error: Was waiting '{', '[', a string, a number, a boolean or null but received ']'
+ --> config.json:1:8
+  |
+1 | [1, 2, ]
+  |        ^
+  = expected: a value
+  = hint: trailing comma is not allowed
+"#;
+        assert_eq!(render_plain(source), expected);
+    }
+
+    #[test]
+    fn snapshot_trailing_comma_in_object() {
+        let source = "{\"a\": true,\n}";
+        let expected = r#"error: Was waiting a string but received '}'
+ --> config.json:2:1
+  |
+2 | }
+  | ^
+  = expected: a string
+  = hint: trailing comma is not allowed
+"#;
+        assert_eq!(render_plain(source), expected);
+    }
+
+    #[test]
+    fn snapshot_unclosed_object_without_file_name() {
+        let source = "{\"a\": [1, 2]";
+        let expected = r#"error: End of stream while waiting ',' or '}'
+ --> 1:13
+  |
+1 | {"a": [1, 2]
+  |             ^
+  = expected: ',' or '}'
+  = hint: unclosed object?
+"#;
+        assert_eq!(render(source, &DiagnosticOptions::default()), expected);
+    }
+
+    #[test]
+    fn snapshot_lexer_error_with_tabulation() {
+        let source = "[\n\t'a']";
+        let expected = "error: The character ''' is unexpected
+ --> config.json:2:2
+  |
+2 | \t'a']
+  | \t^
+  = hint: strings must be enclosed in double quotes
+";
+        assert_eq!(render_plain(source), expected);
+    }
+
+    #[test]
+    fn snapshot_unquoted_key() {
+        let source = "{\"a\": 1, b: 2}";
+        let expected = "error: The character 'b' is unexpected
+ --> config.json:1:10
+  |
+1 | {\"a\": 1, b: 2}
+  |          ^
+";
+        assert_eq!(render_plain(source), expected);
+    }
+
+    #[test]
+    fn snapshot_colored() {
+        let source = "[1 true]";
+        let options = DiagnosticOptions {
+            file_name: None,
+            color: true,
+        };
+        let expected =
+            "\x1b[1;31merror\x1b[0m\x1b[1m: Was waiting ',' or ']' but received a boolean\x1b[0m
+ \x1b[1;34m-->\x1b[0m 1:4
+  \x1b[1;34m|\x1b[0m
+\x1b[1;34m1 |\x1b[0m [1 true]
+  \x1b[1;34m|\x1b[0m    \x1b[1;31m^^^^\x1b[0m
+  \x1b[1;36m= expected:\x1b[0m ',' or ']'
+  \x1b[1;36m= hint:\x1b[0m missing comma?
+";
+        assert_eq!(render(source, &options), expected);
+    }
+
+    #[test]
+    fn foreign_offset() {
+        let error = parse_json("[1 true]").expect_err("Was waiting an error");
+        let rendered = render_diagnostic(&error, "[1", &DiagnosticOptions::default());
+        assert!(rendered
+            .ends_with("1 | [1\n  |   ^\n  = expected: ',' or ']'\n  = hint: missing comma?\n"));
+    }
+
+    #[test]
+    fn snapshot_utf16() {
+        let source: Vec<u8> = "[\"é\" true]"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let error = crate::parse_slice(&source).expect_err("Was waiting an error");
+        assert_eq!(error.context.offset, 10);
+        let expected = r#"error: Was waiting ',' or ']' but received a boolean
+ --> 1:6
+  |
+1 | ["é" true]
+  |      ^^^^
+  = expected: ',' or ']'
+  = hint: missing comma?
+"#;
+        let rendered = render_diagnostic_slice(&error, &source, &DiagnosticOptions::default());
+        assert_eq!(rendered, expected);
+    }
+}
@@ -0,0 +1,45 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+/// Syntax accepted by the lexer and the parser.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dialect {
+    /// Strict RFC 8259 JSON
+    #[default]
+    Json,
+    /// JSON with `//` and `/* */` comments and trailing commas, as used by
+    /// VS Code and TypeScript configuration files
+    Jsonc,
+    /// JSON5, which also has single quoted strings, unquoted keys,
+    /// hexadecimal numbers, `Infinity` and `NaN`
+    Json5,
+}
+
+impl Dialect {
+    pub(crate) fn allows_comments(self) -> bool {
+        self != Dialect::Json
+    }
+
+    pub(crate) fn allows_trailing_commas(self) -> bool {
+        self != Dialect::Json
+    }
+}
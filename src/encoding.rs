@@ -0,0 +1,331 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Handling of the raw bytes given to `parse_slice`: byte order marks, and
+//! detection and transcoding of UTF-16 and UTF-32 input.
+
+use crate::position::{ColumnEncoding, LineIndex};
+use crate::{Context, JsonError, JsonErrorKind};
+use std::fmt;
+
+/// What to do with a byte order mark at the start of the input.
+///
+/// RFC 8259 forbids generating one, but allows parsers to ignore it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BomPolicy {
+    /// Ignore it, positions still count from the start of the input
+    #[default]
+    Skip,
+    /// Report it as an unexpected character
+    Reject,
+}
+
+pub(crate) const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Utf32Le => "UTF-32LE",
+            Encoding::Utf32Be => "UTF-32BE",
+        };
+        f.write_str(name)
+    }
+}
+
+impl Encoding {
+    fn unit_size(self) -> usize {
+        match self {
+            Encoding::Utf8 => 1,
+            Encoding::Utf16Le | Encoding::Utf16Be => 2,
+            Encoding::Utf32Le | Encoding::Utf32Be => 4,
+        }
+    }
+
+    fn read_unit(self, bytes: &[u8]) -> u32 {
+        match self {
+            Encoding::Utf8 => bytes[0] as u32,
+            Encoding::Utf16Le => u16::from_le_bytes([bytes[0], bytes[1]]) as u32,
+            Encoding::Utf16Be => u16::from_be_bytes([bytes[0], bytes[1]]) as u32,
+            Encoding::Utf32Le => u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            Encoding::Utf32Be => u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        }
+    }
+}
+
+/// Guess the encoding from the byte order mark, or else from the pattern of
+/// null bytes described in RFC 4627: the first two characters of a JSON text
+/// are ASCII.
+pub fn detect_encoding(input: &[u8]) -> Encoding {
+    match input {
+        [0xEF, 0xBB, 0xBF, ..] => Encoding::Utf8,
+        [0xFF, 0xFE, 0, 0, ..] | [_, 0, 0, 0, ..] => Encoding::Utf32Le,
+        [0, 0, 0xFE, 0xFF, ..] | [0, 0, 0, _, ..] => Encoding::Utf32Be,
+        [0xFF, 0xFE, ..] | [_, 0, _, 0, ..] | [_, 0] => Encoding::Utf16Le,
+        [0xFE, 0xFF, ..] | [0, _, 0, _, ..] | [0, _] => Encoding::Utf16Be,
+        _ => Encoding::Utf8,
+    }
+}
+
+/// UTF-16 or UTF-32 input transcoded to UTF-8, with the offsets of the
+/// characters in the original input to report positions there.
+pub(crate) struct Transcoded {
+    pub(crate) text: String,
+    /// Offset of each character in the text, and in the original input
+    offsets: Vec<(usize, usize)>,
+}
+
+impl Transcoded {
+    pub(crate) fn new(input: &[u8], encoding: Encoding) -> Result<Self, JsonError> {
+        Self::transcode(input, encoding, false)
+    }
+
+    /// Transcode replacing the invalid bytes with U+FFFD, to display input
+    /// which may not be valid.
+    pub(crate) fn lossy(input: &[u8], encoding: Encoding) -> Self {
+        match Self::transcode(input, encoding, true) {
+            Ok(transcoded) => transcoded,
+            Err(_) => unreachable!("Lossy transcoding does not fail"),
+        }
+    }
+
+    fn transcode(input: &[u8], encoding: Encoding, lossy: bool) -> Result<Self, JsonError> {
+        let unit_size = encoding.unit_size();
+        let mut transcoded = Transcoded {
+            text: String::with_capacity(input.len() / unit_size),
+            offsets: Vec::with_capacity(input.len() / unit_size + 1),
+        };
+        let mut pos = 0;
+        while pos < input.len() {
+            let start = pos;
+            let units = &input[pos..];
+            let c = if units.len() < unit_size {
+                pos = input.len();
+                None
+            } else {
+                let unit = encoding.read_unit(units);
+                pos += unit_size;
+                match unit {
+                    0xD800..=0xDBFF if unit_size == 2 && input.len() - pos >= 2 => {
+                        let low = encoding.read_unit(&input[pos..]);
+                        if (0xDC00..=0xDFFF).contains(&low) {
+                            pos += 2;
+                            std::char::from_u32((unit - 0xD800) * 0x400 + low - 0xDC00 + 0x10000)
+                        } else {
+                            None
+                        }
+                    }
+                    _ => std::char::from_u32(unit),
+                }
+            };
+            let c = match c {
+                Some(c) => c,
+                None if lossy => std::char::REPLACEMENT_CHARACTER,
+                None => return Err(transcoded.invalid(encoding, &input[start..pos], start)),
+            };
+            transcoded.offsets.push((transcoded.text.len(), start));
+            transcoded.text.push(c);
+        }
+        transcoded
+            .offsets
+            .push((transcoded.text.len(), input.len()));
+        Ok(transcoded)
+    }
+
+    /// Error for the invalid bytes found after the text transcoded so far.
+    fn invalid(&self, encoding: Encoding, bytes: &[u8], offset: usize) -> JsonError {
+        let index = LineIndex::new(&self.text);
+        let context = index
+            .context(self.text.len(), ColumnEncoding::Chars)
+            .unwrap_or_default();
+        JsonError::new(
+            JsonErrorKind::InvalidEncoding(encoding, bytes.to_vec()),
+            Context { offset, ..context },
+        )
+    }
+
+    /// Offset in the original input of an offset in the transcoded text.
+    pub(crate) fn original_offset(&self, offset: usize) -> usize {
+        match self
+            .offsets
+            .binary_search_by_key(&offset, |&(text, _)| text)
+        {
+            Ok(idx) => self.offsets[idx].1,
+            // Inside a character, there is always a first one at offset 0
+            Err(idx) => self.offsets[idx - 1].1,
+        }
+    }
+
+    /// Offset in the transcoded text of an offset in the original input.
+    pub(crate) fn text_offset(&self, original: usize) -> usize {
+        match self
+            .offsets
+            .binary_search_by_key(&original, |&(_, original)| original)
+        {
+            Ok(idx) => self.offsets[idx].0,
+            Err(idx) => self.offsets[idx - 1].0,
+        }
+    }
+
+    pub(crate) fn map_error(&self, mut error: JsonError) -> JsonError {
+        error.context.offset = self.original_offset(error.context.offset);
+        error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|unit| unit.to_le_bytes())
+            .collect()
+    }
+
+    fn utf16be(text: &str) -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|unit| unit.to_be_bytes())
+            .collect()
+    }
+
+    fn utf32le(text: &str) -> Vec<u8> {
+        text.chars()
+            .flat_map(|c| (c as u32).to_le_bytes())
+            .collect()
+    }
+
+    fn utf32be(text: &str) -> Vec<u8> {
+        text.chars()
+            .flat_map(|c| (c as u32).to_be_bytes())
+            .collect()
+    }
+
+    type Encoder = fn(&str) -> Vec<u8>;
+
+    #[test]
+    fn detection() {
+        let cases: Vec<(&str, Encoder, Encoding)> = vec![
+            ("[1]", utf16le, Encoding::Utf16Le),
+            ("[1]", utf16be, Encoding::Utf16Be),
+            ("[1]", utf32le, Encoding::Utf32Le),
+            ("[1]", utf32be, Encoding::Utf32Be),
+            ("\u{FEFF}\"é\"", utf16le, Encoding::Utf16Le),
+            ("\u{FEFF}\"é\"", utf16be, Encoding::Utf16Be),
+            ("\u{FEFF}\"é\"", utf32le, Encoding::Utf32Le),
+            ("\u{FEFF}\"é\"", utf32be, Encoding::Utf32Be),
+            ("1", utf16le, Encoding::Utf16Le),
+            ("1", utf16be, Encoding::Utf16Be),
+            ("1", utf32le, Encoding::Utf32Le),
+            ("1", utf32be, Encoding::Utf32Be),
+        ];
+        for (text, encode, encoding) in cases {
+            assert_eq!(detect_encoding(&encode(text)), encoding, "{:?}", text);
+        }
+        assert_eq!(detect_encoding(b"\xEF\xBB\xBF[]"), Encoding::Utf8);
+        assert_eq!(detect_encoding(b"[1]"), Encoding::Utf8);
+        assert_eq!(detect_encoding(b"1"), Encoding::Utf8);
+        assert_eq!(detect_encoding(b""), Encoding::Utf8);
+    }
+
+    #[test]
+    fn transcoding_keeps_original_offsets() {
+        let text = "[\"🐱\", 1]";
+        let input = utf16be(text);
+        let transcoded = Transcoded::new(&input, Encoding::Utf16Be).unwrap();
+        assert_eq!(transcoded.text, text);
+        assert_eq!(transcoded.original_offset(0), 0);
+        assert_eq!(transcoded.original_offset(2), 4);
+        assert_eq!(transcoded.original_offset(6), 8);
+        assert_eq!(transcoded.original_offset(text.len()), input.len());
+        let input = utf32le(text);
+        let transcoded = Transcoded::new(&input, Encoding::Utf32Le).unwrap();
+        assert_eq!(transcoded.text, text);
+        assert_eq!(transcoded.original_offset(6), 12);
+    }
+
+    #[test]
+    fn invalid_input_is_error() {
+        let invalid = |input: &[u8], encoding| {
+            let error = Transcoded::new(input, encoding).err().unwrap();
+            match error.kind {
+                JsonErrorKind::InvalidEncoding(candidate, bytes) => {
+                    assert_eq!(candidate, encoding);
+                    (
+                        bytes,
+                        error.context.line,
+                        error.context.column,
+                        error.context.offset,
+                    )
+                }
+                kind => panic!("Unexpected error {:?}", kind),
+            }
+        };
+        // Lone low surrogate
+        let mut input = utf16le("[\n ");
+        input.extend_from_slice(&[0x00, 0xDC]);
+        assert_eq!(
+            invalid(&input, Encoding::Utf16Le),
+            (vec![0x00, 0xDC], 2, 2, 6)
+        );
+        // High surrogate followed by a quote
+        assert_eq!(
+            invalid(&[0xD8, 0x3D, 0x00, 0x22], Encoding::Utf16Be),
+            (vec![0xD8, 0x3D], 1, 1, 0)
+        );
+        // Odd number of bytes
+        assert_eq!(
+            invalid(&[0x31, 0x00, 0x32], Encoding::Utf16Le),
+            (vec![0x32], 1, 2, 2)
+        );
+        assert_eq!(
+            invalid(&[0, 0, 0x11, 0], Encoding::Utf32Le),
+            (vec![0, 0, 0x11, 0], 1, 1, 0)
+        );
+    }
+
+    #[test]
+    fn lossy() {
+        let mut input = utf16le("[\"");
+        input.extend_from_slice(&[0x00, 0xDC]);
+        input.extend(utf16le("\", é]"));
+        let transcoded = Transcoded::lossy(&input, Encoding::Utf16Le);
+        assert_eq!(transcoded.text, "[\"\u{FFFD}\", é]");
+        // The replacement character is three bytes long
+        assert_eq!(transcoded.text_offset(6), 5);
+        assert_eq!(transcoded.text_offset(12), 8);
+        assert_eq!(transcoded.original_offset(8), 12);
+        // Inside the character 'é'
+        assert_eq!(transcoded.text_offset(13), 8);
+    }
+}
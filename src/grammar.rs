@@ -0,0 +1,397 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! The JSON grammar, as one iterative state machine fed token by token, which
+//! hands what it reads to a `Builder`. The parser, the spanned, validating and
+//! concrete syntax tree parsers, and the incremental and recovering parsers all
+//! read documents through it.
+
+use crate::lexer::{Lexer, Token, TokenInfo, TokenKind};
+use crate::{Context, Dialect, JsonError, JsonErrorKind, Limit, ParseLimits};
+
+pub(crate) const ARRAY_FIRST: &[TokenKind] = &[
+    TokenKind::ObjectStart,
+    TokenKind::ArrayStart,
+    TokenKind::ValueString,
+    TokenKind::ValueNumber,
+    TokenKind::ValueBoolean,
+    TokenKind::ValueNull,
+    TokenKind::ArrayEnd,
+];
+pub(crate) const ARRAY_NEXT: &[TokenKind] = &[TokenKind::SeparatorValue, TokenKind::ArrayEnd];
+pub(crate) const OBJECT_FIRST: &[TokenKind] = &[TokenKind::ValueString, TokenKind::ObjectEnd];
+pub(crate) const OBJECT_NEXT: &[TokenKind] = &[TokenKind::SeparatorValue, TokenKind::ObjectEnd];
+pub(crate) const OBJECT_KEY: &[TokenKind] = &[TokenKind::ValueString];
+pub(crate) const OBJECT_SEPARATOR: &[TokenKind] = &[TokenKind::SeparatorName];
+
+/// Tokens valid after a comma in a container, `with_end` being the ones at
+/// its start, which are used when the dialect allows trailing commas.
+pub(crate) fn expected_after_comma(
+    dialect: Dialect,
+    with_end: &'static [TokenKind],
+) -> &'static [TokenKind] {
+    if dialect.allows_trailing_commas() {
+        return with_end;
+    }
+    match with_end {
+        ARRAY_FIRST => TokenKind::VALUE_START,
+        _ => OBJECT_KEY,
+    }
+}
+
+/// Receives the values read by a `Grammar`, to build them in its own form.
+///
+/// Every token of the document is given to one of the methods, in order.
+pub(crate) trait Builder {
+    type Value;
+    type Array;
+    type Object;
+
+    /// A string, number, boolean or null.
+    fn scalar(&mut self, token_info: &TokenInfo) -> Self::Value;
+
+    fn start_array(&mut self, open: &TokenInfo) -> Self::Array;
+
+    fn push_element(&mut self, array: &mut Self::Array, value: Self::Value);
+
+    fn array_comma(&mut self, _array: &mut Self::Array, _comma: &TokenInfo) {}
+
+    fn end_array(&mut self, array: Self::Array, close: &TokenInfo) -> Self::Value;
+
+    fn start_object(&mut self, open: &TokenInfo) -> Self::Object;
+
+    /// The key of the next member, unescaped.
+    fn key(&mut self, object: &mut Self::Object, key: String, token_info: &TokenInfo);
+
+    fn colon(&mut self, _object: &mut Self::Object, _colon: &TokenInfo) {}
+
+    /// Add the value of the member whose key was read.
+    fn push_member(&mut self, object: &mut Self::Object, value: Self::Value);
+
+    fn object_comma(&mut self, _object: &mut Self::Object, _comma: &TokenInfo) {}
+
+    fn end_object(&mut self, object: Self::Object, close: &TokenInfo) -> Self::Value;
+
+    /// Drop a container left open when the grammar is dropped, after an
+    /// error for instance, its values being possibly deeply nested.
+    fn discard(&mut self, _frame: Frame<Self::Array, Self::Object>) {}
+}
+
+/// Array or object being read, kept on an explicit stack so that deeply
+/// nested documents cannot overflow the call stack.
+pub(crate) enum Frame<A, O> {
+    Array(A),
+    Object(O),
+}
+
+/// What the grammar waits for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum State {
+    /// A value, or one of the given tokens
+    Value(&'static [TokenKind]),
+    ArrayNext,
+    /// A key, or one of the given tokens
+    Key(&'static [TokenKind]),
+    Colon,
+    ObjectNext,
+    Done,
+}
+
+pub(crate) struct Grammar<B: Builder> {
+    pub(crate) builder: B,
+    pub(crate) stack: Vec<Frame<B::Array, B::Object>>,
+    /// Number of values of each container of `stack`, counted here so that
+    /// repeated keys count whatever the builder keeps
+    counts: Vec<usize>,
+    pub(crate) state: State,
+    root: Option<B::Value>,
+    limits: ParseLimits,
+    dialect: Dialect,
+    total_values: usize,
+}
+
+impl<B: Builder> Drop for Grammar<B> {
+    fn drop(&mut self) {
+        while let Some(frame) = self.stack.pop() {
+            self.builder.discard(frame);
+        }
+    }
+}
+
+impl<B: Builder> Grammar<B> {
+    /// Only the depth, container elements and total values limits apply to
+    /// the grammar, the others are checked by the lexer.
+    pub(crate) fn new(builder: B, limits: ParseLimits, dialect: Dialect) -> Self {
+        Grammar {
+            builder,
+            stack: Vec::new(),
+            counts: Vec::new(),
+            state: State::Value(TokenKind::VALUE_START),
+            root: None,
+            limits,
+            dialect,
+            total_values: 0,
+        }
+    }
+
+    pub(crate) fn is_done(&self) -> bool {
+        self.state == State::Done
+    }
+
+    pub(crate) fn root(&self) -> Option<&B::Value> {
+        self.root.as_ref()
+    }
+
+    pub(crate) fn into_root(mut self) -> Option<B::Value> {
+        self.root.take()
+    }
+
+    /// Whether no token was read yet.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.stack.is_empty() && matches!(self.state, State::Value(_))
+    }
+
+    /// The tokens valid next, none once the value is complete.
+    pub(crate) fn expected(&self) -> &'static [TokenKind] {
+        match self.state {
+            State::Value(expected) | State::Key(expected) => expected,
+            State::ArrayNext => ARRAY_NEXT,
+            State::Colon => OBJECT_SEPARATOR,
+            State::ObjectNext => OBJECT_NEXT,
+            State::Done => &[],
+        }
+    }
+
+    /// The error for an input ending at `context` before the end of the value.
+    pub(crate) fn eof_error(&self, context: Context) -> JsonError {
+        if self.is_empty() {
+            return JsonError::new(JsonErrorKind::EmptyInput, context);
+        }
+        JsonError::new(
+            JsonErrorKind::UnexpectedEof {
+                expected: self.expected().to_vec(),
+            },
+            context,
+        )
+    }
+
+    /// Read the next token, `lexer` being the one which read it.
+    pub(crate) fn token(&mut self, lexer: &Lexer, token_info: &TokenInfo) -> Result<(), JsonError> {
+        match self.state {
+            State::Value(expected) => match &token_info.token {
+                Token::ArrayStart => {
+                    self.check_depth(token_info)?;
+                    let array = self.builder.start_array(token_info);
+                    self.stack.push(Frame::Array(array));
+                    self.counts.push(0);
+                    self.state = State::Value(ARRAY_FIRST);
+                }
+                Token::ObjectStart => {
+                    self.check_depth(token_info)?;
+                    let object = self.builder.start_object(token_info);
+                    self.stack.push(Frame::Object(object));
+                    self.counts.push(0);
+                    self.state = State::Key(OBJECT_FIRST);
+                }
+                Token::ValueNull
+                | Token::ValueNumber(_)
+                | Token::ValueCustomNumber(_)
+                | Token::ValueBoolean(_)
+                | Token::ValueString(_)
+                | Token::ValueWtf8String(_) => {
+                    let value = self.builder.scalar(token_info);
+                    self.complete(value, &token_info.context)?;
+                }
+                // The end is valid first, and after a comma when trailing ones are
+                Token::ArrayEnd if expected.contains(&TokenKind::ArrayEnd) => {
+                    self.close(token_info)?
+                }
+                _ => return Err(unexpected(token_info, expected)),
+            },
+            State::ArrayNext => match token_info.token {
+                Token::SeparatorValue => {
+                    if let Some(Frame::Array(array)) = self.stack.last_mut() {
+                        self.builder.array_comma(array, token_info);
+                    }
+                    self.state = State::Value(expected_after_comma(self.dialect, ARRAY_FIRST));
+                }
+                Token::ArrayEnd => self.close(token_info)?,
+                _ => return Err(unexpected(token_info, ARRAY_NEXT)),
+            },
+            State::Key(expected) => match lexer.key(token_info)? {
+                Some(key) => self.set_key(key, token_info),
+                None if expected.contains(&TokenKind::ObjectEnd)
+                    && token_info.token == Token::ObjectEnd =>
+                {
+                    self.close(token_info)?
+                }
+                None => return Err(unexpected(token_info, expected)),
+            },
+            State::Colon => match token_info.token {
+                Token::SeparatorName => {
+                    if let Some(Frame::Object(object)) = self.stack.last_mut() {
+                        self.builder.colon(object, token_info);
+                    }
+                    self.state = State::Value(TokenKind::VALUE_START);
+                }
+                _ => return Err(unexpected(token_info, OBJECT_SEPARATOR)),
+            },
+            State::ObjectNext => match token_info.token {
+                Token::SeparatorValue => {
+                    if let Some(Frame::Object(object)) = self.stack.last_mut() {
+                        self.builder.object_comma(object, token_info);
+                    }
+                    self.state = State::Key(expected_after_comma(self.dialect, OBJECT_FIRST));
+                }
+                Token::ObjectEnd => self.close(token_info)?,
+                _ => return Err(unexpected(token_info, OBJECT_NEXT)),
+            },
+            State::Done => {
+                return Err(JsonError::new(
+                    JsonErrorKind::TrailingToken(token_info.token.kind()),
+                    token_info.context.clone(),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Attach a completed value to the current container, `context` being
+    /// where to report an exceeded limit.
+    pub(crate) fn complete(&mut self, value: B::Value, context: &Context) -> Result<(), JsonError> {
+        // The value is attached even past a limit, for the recovering parser
+        match self.stack.last_mut() {
+            None => {
+                self.root = Some(value);
+                self.state = State::Done;
+            }
+            Some(Frame::Array(array)) => {
+                self.state = State::ArrayNext;
+                self.builder.push_element(array, value);
+            }
+            Some(Frame::Object(object)) => {
+                self.state = State::ObjectNext;
+                self.builder.push_member(object, value);
+            }
+        }
+        let count = self.counts.last_mut().map(|count| {
+            *count += 1;
+            *count
+        });
+        self.total_values += 1;
+        match (
+            self.limits.max_total_values,
+            self.limits.max_container_elements,
+        ) {
+            (Some(max), _) if self.total_values > max => {
+                Err(limit_error(Limit::TotalValues(max), context))
+            }
+            (_, Some(max)) if count > Some(max) => {
+                Err(limit_error(Limit::ContainerElements(max), context))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Set the key of the current object, and wait its colon.
+    pub(crate) fn set_key(&mut self, key: String, token_info: &TokenInfo) {
+        if let Some(Frame::Object(object)) = self.stack.last_mut() {
+            self.builder.key(object, key, token_info);
+        }
+        self.state = State::Colon;
+    }
+
+    /// Close the current container with `close`, and attach it to its parent.
+    pub(crate) fn close(&mut self, close: &TokenInfo) -> Result<(), JsonError> {
+        self.counts.pop();
+        let value = match self.stack.pop() {
+            Some(Frame::Array(array)) => self.builder.end_array(array, close),
+            Some(Frame::Object(object)) => self.builder.end_object(object, close),
+            None => return Ok(()),
+        };
+        self.complete(value, &close.context)
+    }
+
+    fn check_depth(&self, token_info: &TokenInfo) -> Result<(), JsonError> {
+        match self.limits.max_depth {
+            Some(max) if self.stack.len() >= max => {
+                Err(limit_error(Limit::Depth(max), &token_info.context))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+pub(crate) fn unexpected(token_info: &TokenInfo, expected: &[TokenKind]) -> JsonError {
+    JsonError::new(
+        JsonErrorKind::UnexpectedToken {
+            found: token_info.token.kind(),
+            expected: expected.to_vec(),
+        },
+        token_info.context.clone(),
+    )
+}
+
+fn limit_error(limit: Limit, context: &Context) -> JsonError {
+    JsonError::new(JsonErrorKind::LimitExceeded(limit), context.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cst::parse_document;
+    use crate::spanned::parse_json_spanned_with_limits;
+    use crate::validate::validate_with_limits;
+    use crate::{parse_json_recovering_with_limits, parse_json_with_limits, Dialect, ParseLimits};
+
+    #[test]
+    fn every_reader_has_the_same_errors() {
+        let limits = ParseLimits {
+            max_depth: Some(3),
+            max_container_elements: Some(3),
+            max_total_values: Some(8),
+            ..ParseLimits::unlimited()
+        };
+        let inputs = [
+            "[1, [2, {\"a\": null}], true]",
+            "[[[[1]]]]",
+            "[1, 2, 3, 4]",
+            "{\"a\": 1, \"b\": 2, \"c\": 3, \"d\": 4}",
+            "{\"a\": 1, \"a\": 2, \"a\": 3, \"a\": 4}",
+            "[[1, 2], [3, 4], [5, 6]]",
+            "{\"a\" 1}",
+            "[1,]",
+            "[1] 2",
+            "",
+        ];
+        for input in inputs.iter() {
+            let error = parse_json_with_limits(input, limits.clone()).err();
+            let spanned = parse_json_spanned_with_limits(input, limits.clone()).err();
+            let validated = validate_with_limits(input, limits.clone()).err();
+            let recovered = parse_json_recovering_with_limits(input, 10, limits.clone());
+            let cst = parse_document(input, limits.clone(), Dialect::Json, Default::default());
+            assert_eq!(spanned, error, "input: {}", input);
+            assert_eq!(validated, error, "input: {}", input);
+            assert_eq!(cst.err(), error, "input: {}", input);
+            assert_eq!(recovered.errors.first(), error.as_ref(), "input: {}", input);
+        }
+    }
+}
@@ -0,0 +1,334 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Syntax highlighting which never stops at errors: the bad parts of the input
+//! are highlighted as errors and the lexer resumes after them.
+
+use crate::lexer::{Lexer, Token};
+use crate::{Context, Dialect, Span};
+use std::fmt::Write;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HighlightKind {
+    /// Brackets, braces, ':' and ','
+    Punctuation,
+    Key,
+    String,
+    Number,
+    Boolean,
+    Null,
+    Comment,
+    Whitespace,
+    Error,
+}
+
+impl HighlightKind {
+    fn class_name(self) -> &'static str {
+        match self {
+            HighlightKind::Punctuation => "json-punctuation",
+            HighlightKind::Key => "json-key",
+            HighlightKind::String => "json-string",
+            HighlightKind::Number => "json-number",
+            HighlightKind::Boolean => "json-boolean",
+            HighlightKind::Null => "json-null",
+            HighlightKind::Comment => "json-comment",
+            HighlightKind::Whitespace => "json-whitespace",
+            HighlightKind::Error => "json-error",
+        }
+    }
+
+    fn ansi_code(self) -> Option<&'static str> {
+        match self {
+            HighlightKind::Punctuation | HighlightKind::Whitespace => None,
+            HighlightKind::Key => Some("\x1b[1;34m"),
+            HighlightKind::String => Some("\x1b[32m"),
+            HighlightKind::Number => Some("\x1b[36m"),
+            HighlightKind::Boolean | HighlightKind::Null => Some("\x1b[35m"),
+            HighlightKind::Comment => Some("\x1b[90m"),
+            HighlightKind::Error => Some("\x1b[4;31m"),
+        }
+    }
+}
+
+/// A highlighted part of the input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Highlight {
+    pub kind: HighlightKind,
+    pub span: Span,
+}
+
+/// Split the whole input in highlighted parts, in order and without gaps.
+///
+/// A string is a key when it is followed by a ':'. After an error, the lexer
+/// resumes after the string or the word where it happened.
+pub fn highlight(input: &str, dialect: Dialect) -> Vec<Highlight> {
+    let mut lexer = Lexer::new(input);
+    lexer.set_dialect(dialect);
+    lexer.set_trivia(true);
+    let mut highlights: Vec<Highlight> = Vec::new();
+    // Index of the last highlight which is not whitespace or a comment
+    let mut last_significant = None;
+    while let Some(result) = lexer.next() {
+        let token_info = match result {
+            Ok(token_info) => token_info,
+            Err(_) => {
+                let start = lexer.token_start().clone();
+                lexer.skip_to(resume_offset(input, &start, lexer.context()));
+                let span = Span {
+                    start,
+                    end: lexer.context().clone(),
+                };
+                match highlights.last_mut() {
+                    // One error for the whole bad part
+                    Some(last) if last.kind == HighlightKind::Error => last.span.end = span.end,
+                    _ => highlights.push(Highlight {
+                        kind: HighlightKind::Error,
+                        span,
+                    }),
+                }
+                last_significant = Some(highlights.len() - 1);
+                continue;
+            }
+        };
+        let kind = match token_info.token {
+            Token::ArrayStart
+            | Token::ArrayEnd
+            | Token::ObjectStart
+            | Token::ObjectEnd
+            | Token::SeparatorValue => HighlightKind::Punctuation,
+            Token::SeparatorName => {
+                if let Some(idx) = last_significant {
+                    if highlights[idx].kind == HighlightKind::String {
+                        highlights[idx].kind = HighlightKind::Key;
+                    }
+                }
+                HighlightKind::Punctuation
+            }
+            Token::ValueNull => HighlightKind::Null,
+            Token::ValueNumber(_) | Token::ValueCustomNumber(_) => HighlightKind::Number,
+            Token::ValueBoolean(_) => HighlightKind::Boolean,
+            Token::ValueString(_) | Token::ValueWtf8String(_) | Token::StringChunk(_) => {
+                HighlightKind::String
+            }
+            Token::Identifier(_) => HighlightKind::Key,
+            Token::Whitespace => HighlightKind::Whitespace,
+            Token::Comment => HighlightKind::Comment,
+        };
+        if kind != HighlightKind::Whitespace && kind != HighlightKind::Comment {
+            last_significant = Some(highlights.len());
+        }
+        highlights.push(Highlight {
+            kind,
+            span: token_info.span(),
+        });
+    }
+    highlights
+}
+
+/// Where to resume after an error in the token starting at `start`, the lexer
+/// being at `current`: after the rest of a string on the same line, or after
+/// the rest of a word.
+fn resume_offset(input: &str, start: &Context, current: &Context) -> usize {
+    let bytes = input.as_bytes();
+    let mut pos = current.offset;
+    match bytes.get(start.offset).copied().unwrap_or(b' ') {
+        quote @ (b'"' | b'\'') if start.line == current.line => {
+            while pos < bytes.len() && bytes[pos] != b'\n' {
+                pos += 1;
+                match bytes[pos - 1] {
+                    b'\\' if pos < bytes.len() && bytes[pos] != b'\n' => pos += 1,
+                    b if b == quote => break,
+                    _ => (),
+                }
+            }
+        }
+        b'"' | b'\'' => (),
+        _ => {
+            while pos < bytes.len()
+                && (bytes[pos].is_ascii_alphanumeric() || b"_.+-".contains(&bytes[pos]))
+            {
+                pos += 1;
+            }
+        }
+    }
+    // Never stop inside a character
+    while pos < bytes.len() && !input.is_char_boundary(pos) {
+        pos += 1;
+    }
+    pos
+}
+
+/// Render the highlighted input with ANSI escape codes, for a terminal.
+pub fn render_highlights_ansi(input: &str, highlights: &[Highlight]) -> String {
+    let mut output = String::with_capacity(input.len());
+    for highlight in highlights {
+        let text = &input[highlight.span.start.offset..highlight.span.end.offset];
+        match highlight.kind.ansi_code() {
+            Some(code) => write!(output, "{}{}\x1b[0m", code, text).unwrap(),
+            None => output.push_str(text),
+        }
+    }
+    output
+}
+
+/// Render the highlighted input as HTML, each part in a `<span>` with a
+/// `json-` class such as `json-key`, to be styled with CSS.
+pub fn render_highlights_html(input: &str, highlights: &[Highlight]) -> String {
+    let mut output = String::with_capacity(input.len());
+    for highlight in highlights {
+        let text = &input[highlight.span.start.offset..highlight.span.end.offset];
+        if highlight.kind == HighlightKind::Whitespace {
+            output.push_str(text);
+            continue;
+        }
+        write!(output, "<span class=\"{}\">", highlight.kind.class_name()).unwrap();
+        for c in text.chars() {
+            match c {
+                '&' => output.push_str("&amp;"),
+                '<' => output.push_str("&lt;"),
+                '>' => output.push_str("&gt;"),
+                '"' => output.push_str("&quot;"),
+                '\'' => output.push_str("&#39;"),
+                c => output.push(c),
+            }
+        }
+        output.push_str("</span>");
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(input: &str, dialect: Dialect) -> Vec<(HighlightKind, &str)> {
+        highlight(input, dialect)
+            .iter()
+            .map(|h| (h.kind, &input[h.span.start.offset..h.span.end.offset]))
+            .collect()
+    }
+
+    #[test]
+    fn keys_and_values() {
+        use HighlightKind::*;
+        assert_eq!(
+            parts(r#"{"a": ["b", 1, true, null]}"#, Dialect::Json),
+            vec![
+                (Punctuation, "{"),
+                (Key, "\"a\""),
+                (Punctuation, ":"),
+                (Whitespace, " "),
+                (Punctuation, "["),
+                (String, "\"b\""),
+                (Punctuation, ","),
+                (Whitespace, " "),
+                (Number, "1"),
+                (Punctuation, ","),
+                (Whitespace, " "),
+                (Boolean, "true"),
+                (Punctuation, ","),
+                (Whitespace, " "),
+                (Null, "null"),
+                (Punctuation, "]"),
+                (Punctuation, "}"),
+            ]
+        );
+        assert_eq!(
+            parts("{a /* c */ : 'b'}", Dialect::Json5),
+            vec![
+                (Punctuation, "{"),
+                (Key, "a"),
+                (Whitespace, " "),
+                (Comment, "/* c */"),
+                (Whitespace, " "),
+                (Punctuation, ":"),
+                (Whitespace, " "),
+                (String, "'b'"),
+                (Punctuation, "}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_do_not_stop_highlighting() {
+        use HighlightKind::*;
+        assert_eq!(
+            parts(r#"[trux, "a\qb", @@é, 'c', 2]"#, Dialect::Json),
+            vec![
+                (Punctuation, "["),
+                (Error, "trux"),
+                (Punctuation, ","),
+                (Whitespace, " "),
+                (Error, r#""a\qb""#),
+                (Punctuation, ","),
+                (Whitespace, " "),
+                (Error, "@@é"),
+                (Punctuation, ","),
+                (Whitespace, " "),
+                (Error, "'c'"),
+                (Punctuation, ","),
+                (Whitespace, " "),
+                (Number, "2"),
+                (Punctuation, "]"),
+            ]
+        );
+        // An unterminated string stops at the end of its line
+        assert_eq!(
+            parts("\"a\n1", Dialect::Json),
+            vec![(Error, "\"a\n"), (Number, "1")]
+        );
+    }
+
+    #[test]
+    fn highlights_cover_the_input() {
+        let input = "{\"é\": [1, -, \"\\u12\"], /* x */ nul}\n";
+        let highlights = highlight(input, Dialect::Jsonc);
+        let mut offset = 0;
+        for h in &highlights {
+            assert_eq!(h.span.start.offset, offset);
+            offset = h.span.end.offset;
+        }
+        assert_eq!(offset, input.len());
+    }
+
+    #[test]
+    fn render_ansi() {
+        let input = r#"{"a": 1}"#;
+        assert_eq!(
+            render_highlights_ansi(input, &highlight(input, Dialect::Json)),
+            "{\x1b[1;34m\"a\"\x1b[0m: \x1b[36m1\x1b[0m}"
+        );
+    }
+
+    #[test]
+    fn render_html() {
+        let input = r#"{"<a>": x}"#;
+        assert_eq!(
+            render_highlights_html(input, &highlight(input, Dialect::Json)),
+            "<span class=\"json-punctuation\">{</span>\
+             <span class=\"json-key\">&quot;&lt;a&gt;&quot;</span>\
+             <span class=\"json-punctuation\">:</span> \
+             <span class=\"json-error\">x</span>\
+             <span class=\"json-punctuation\">}</span>"
+        );
+    }
+}
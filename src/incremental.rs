@@ -0,0 +1,534 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Parser fed with successive chunks of a document, which can give a snapshot
+//! of the value read so far, for interfaces displaying a document while it
+//! streams in.
+
+use crate::grammar::{Frame, Grammar, State};
+use crate::lexer::{Lexer, Token};
+use crate::parser::JsonBuilder;
+use crate::{Context, Dialect, Json, JsonError, JsonErrorKind, Limit, ParseLimits};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// The value read so far by an `IncrementalParser`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    /// The value with its open arrays and objects closed, `None` before the
+    /// start of the value
+    pub value: Option<Json>,
+    /// Paths to the values which are not complete yet, the open arrays and
+    /// objects, and the string or number being read. A path has the keys and
+    /// indexes from the root, as with `CstValue::pointer_mut`.
+    pub incomplete: Vec<Vec<String>>,
+}
+
+/// Parser which reads a document chunk by chunk.
+///
+/// Each chunk is lexed once: a string cut by the end of a chunk is kept as
+/// read so far, and only a number or literal cut by it is read again with the
+/// next one. The chunks are dropped once read.
+pub struct IncrementalParser {
+    /// The input not read yet
+    buffer: String,
+    /// Position of the start of `buffer` in the document
+    base: Context,
+    grammar: Grammar<JsonBuilder>,
+    limits: ParseLimits,
+    /// The string cut by the end of the input, with its position
+    string: Option<(Context, String)>,
+    /// The number cut by the end of the input, as read so far
+    partial: Option<Json>,
+    error: Option<JsonError>,
+}
+
+impl Default for IncrementalParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IncrementalParser {
+    /// Create a parser with the default limits, see `ParseLimits`.
+    pub fn new() -> Self {
+        Self::with_limits(ParseLimits::default())
+    }
+
+    /// Create a parser with the given limits, `max_input_bytes` bounding the
+    /// size of the whole document.
+    pub fn with_limits(limits: ParseLimits) -> Self {
+        IncrementalParser {
+            buffer: String::new(),
+            base: Context::default(),
+            grammar: Grammar::new(JsonBuilder, limits.clone(), Dialect::Json),
+            limits,
+            string: None,
+            partial: None,
+            error: None,
+        }
+    }
+
+    /// Read the next chunk of the document. After an error, the same error is
+    /// returned again.
+    pub fn feed(&mut self, chunk: &str) -> Result<(), JsonError> {
+        if let Some(error) = &self.error {
+            return Err(error.clone());
+        }
+        if let Some(max) = self.limits.max_input_bytes {
+            if self.base.offset + self.buffer.len() + chunk.len() > max {
+                let error = JsonError::new(
+                    JsonErrorKind::LimitExceeded(Limit::InputBytes(max)),
+                    self.base.clone(),
+                );
+                self.error = Some(error.clone());
+                return Err(error);
+            }
+        }
+        self.buffer.push_str(chunk);
+        self.read(false)
+    }
+
+    /// Whether the whole value has been read.
+    pub fn is_complete(&self) -> bool {
+        self.grammar.is_done()
+    }
+
+    /// End the document, returning its value.
+    pub fn finish(mut self) -> Result<Json, JsonError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        self.read(true)?;
+        if !self.grammar.is_done() {
+            return Err(self.grammar.eof_error(self.base));
+        }
+        Ok(self.grammar.into_root().unwrap_or(Json::Null))
+    }
+
+    /// The value read so far, with the paths of its incomplete parts.
+    ///
+    /// The value is built anew from the values read so far, so each snapshot
+    /// costs a copy of them: take snapshots when they are displayed rather
+    /// than after each chunk.
+    pub fn snapshot(&self) -> Snapshot {
+        let mut incomplete = Vec::new();
+        let mut path = Vec::new();
+        let stack = &self.grammar.stack;
+        if !stack.is_empty() {
+            incomplete.push(Vec::new());
+        }
+        for frames in stack.windows(2) {
+            path.push(next_segment(&frames[0]));
+            incomplete.push(path.clone());
+        }
+        let mut value = match self.grammar.state {
+            // The key of a partial string is complete
+            State::Value(_) => match &self.string {
+                Some((_, text)) => Some(Json::String(text.clone())),
+                None => self.partial.clone(),
+            },
+            _ => None,
+        };
+        if value.is_some() {
+            if let Some(frame) = stack.last() {
+                path.push(next_segment(frame));
+            }
+            incomplete.push(path);
+        }
+        for frame in stack.iter().rev() {
+            let container = match frame {
+                Frame::Array(vec) => {
+                    let mut vec = vec.clone();
+                    vec.extend(value.take());
+                    Json::Array(vec)
+                }
+                Frame::Object((map, key)) => {
+                    let mut map = map.clone();
+                    if let Some(value) = value.take() {
+                        map.insert(key.clone(), value);
+                    }
+                    Json::Object(map)
+                }
+            };
+            value = Some(container);
+        }
+        Snapshot {
+            value: value.or_else(|| self.grammar.root().cloned()),
+            incomplete,
+        }
+    }
+
+    /// Handle the tokens of `buffer`, stopping before a token which may be
+    /// cut, unless this is the `last` chunk, and drop what was read.
+    fn read(&mut self, last: bool) -> Result<(), JsonError> {
+        let buffer = std::mem::take(&mut self.buffer);
+        let result = self.read_tokens(&buffer, last);
+        self.buffer = buffer;
+        match result {
+            Ok(consumed) => {
+                self.buffer.drain(..consumed.offset);
+                self.base = self.translate(&consumed);
+                Ok(())
+            }
+            Err(error) => {
+                self.error = Some(error.clone());
+                Err(error)
+            }
+        }
+    }
+
+    /// Handle the tokens of `input`, returning the position after the last
+    /// one handled.
+    fn read_tokens(&mut self, input: &str, last: bool) -> Result<Context, JsonError> {
+        let mut lexer = Lexer::new(input);
+        lexer.set_limits(self.limits.clone());
+        lexer.set_cut_strings(!last);
+        if self.string.is_some() {
+            lexer.set_open_string(b'"');
+        }
+        self.partial = None;
+        loop {
+            let result = match lexer.next() {
+                None => return Ok(lexer.context().clone()),
+                Some(result) => result,
+            };
+            let at_end = lexer.context().offset == input.len();
+            let is_cut = match &result {
+                Ok(token_info) => matches!(token_info.token, Token::ValueNumber(_)),
+                Err(error) => matches!(
+                    error.kind,
+                    JsonErrorKind::UnexpectedEof { .. } | JsonErrorKind::InvalidNumber(_)
+                ),
+            };
+            if at_end && is_cut && !last {
+                let start = lexer.token_start().clone();
+                self.partial = partial_number(&input[start.offset..]);
+                return Ok(start);
+            }
+            let mut token_info = result.map_err(|error| self.translate_error(error))?;
+            let start = match token_info.token {
+                Token::StringChunk(ref text) => {
+                    let context = self.translate(&token_info.context);
+                    let (start, string) = self.string.get_or_insert((context, String::new()));
+                    string.push_str(text);
+                    check_string_length(string, start, &self.limits)?;
+                    // The rest of the string is in the next chunk
+                    return Ok(lexer.context().clone());
+                }
+                Token::ValueString(ref mut text) => match self.string.take() {
+                    Some((start, mut string)) => {
+                        string.push_str(text);
+                        *text = string;
+                        check_string_length(text, &start, &self.limits)?;
+                        Some(start)
+                    }
+                    None => None,
+                },
+                _ => None,
+            };
+            self.grammar
+                .token(&lexer, &token_info)
+                .map_err(|error| match &start {
+                    // The error is at the string, started in a previous chunk
+                    Some(start) => JsonError::new(error.kind, start.clone()),
+                    None => self.translate_error(error),
+                })?;
+        }
+    }
+
+    /// Position in the whole document of a position in the chunk read.
+    fn translate(&self, context: &Context) -> Context {
+        Context {
+            line: self.base.line + context.line - 1,
+            column: match context.line {
+                1 => self.base.column + context.column - 1,
+                _ => context.column,
+            },
+            offset: self.base.offset + context.offset,
+        }
+    }
+
+    fn translate_error(&self, error: JsonError) -> JsonError {
+        JsonError::new(error.kind, self.translate(&error.context))
+    }
+}
+
+/// Check the length of a string read in several chunks, the lexer only
+/// checking each of them.
+fn check_string_length(
+    string: &str,
+    start: &Context,
+    limits: &ParseLimits,
+) -> Result<(), JsonError> {
+    match limits.max_string_length {
+        Some(max) if string.len() > max => Err(JsonError::new(
+            JsonErrorKind::LimitExceeded(Limit::StringLength(max)),
+            start.clone(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Path segment of the next value of a container.
+fn next_segment(frame: &Frame<Vec<Json>, (HashMap<String, Json>, String)>) -> String {
+    match frame {
+        Frame::Array(vec) => vec.len().to_string(),
+        Frame::Object((_, key)) => key.clone(),
+    }
+}
+
+/// The value of a number cut by the end of the input, if it has one already.
+fn partial_number(raw: &str) -> Option<Json> {
+    match raw.as_bytes().first()? {
+        b'-' | b'0'..=b'9' => {
+            // Without an incomplete fraction or exponent
+            let number = raw.trim_end_matches(['.', 'e', 'E', '+', '-']);
+            f64::from_str(number).ok().map(Json::Number)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grammar::ARRAY_NEXT;
+    use crate::lexer::TokenKind;
+    use crate::parse_json;
+
+    fn json(input: &str) -> Json {
+        parse_json(input).unwrap()
+    }
+
+    fn path(segments: &[&str]) -> Vec<String> {
+        segments.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn snapshots_close_open_containers() {
+        let mut parser = IncrementalParser::new();
+        assert_eq!(parser.snapshot().value, None);
+        parser.feed(r#"{"a": [1, {"b": "hel"#).unwrap();
+        let snapshot = parser.snapshot();
+        assert_eq!(snapshot.value, Some(json(r#"{"a": [1, {"b": "hel"}]}"#)));
+        assert_eq!(
+            snapshot.incomplete,
+            vec![
+                path(&[]),
+                path(&["a"]),
+                path(&["a", "1"]),
+                path(&["a", "1", "b"])
+            ]
+        );
+        parser.feed(r#"lo"}, 2"#).unwrap();
+        let snapshot = parser.snapshot();
+        assert_eq!(
+            snapshot.value,
+            Some(json(r#"{"a": [1, {"b": "hello"}, 2]}"#))
+        );
+        // The number may have more digits
+        assert_eq!(
+            snapshot.incomplete,
+            vec![path(&[]), path(&["a"]), path(&["a", "2"])]
+        );
+        parser.feed("3]}").unwrap();
+        assert!(parser.is_complete());
+        let snapshot = parser.snapshot();
+        assert_eq!(
+            snapshot.value,
+            Some(json(r#"{"a": [1, {"b": "hello"}, 23]}"#))
+        );
+        assert!(snapshot.incomplete.is_empty());
+        assert_eq!(
+            parser.finish().unwrap(),
+            json(r#"{"a": [1, {"b": "hello"}, 23]}"#)
+        );
+    }
+
+    #[test]
+    fn cut_tokens() {
+        let mut parser = IncrementalParser::new();
+        parser.feed(r#"[tr"#).unwrap();
+        assert_eq!(parser.snapshot().value, Some(json("[]")));
+        parser.feed(r#"ue, -1.5e"#).unwrap();
+        assert_eq!(parser.snapshot().value, Some(json("[true, -1.5]")));
+        parser.feed(r#"2, "\u00"#).unwrap();
+        assert_eq!(parser.snapshot().value, Some(json(r#"[true, -150, ""]"#)));
+        parser.feed(r#"e9"]"#).unwrap();
+        assert_eq!(parser.finish().unwrap(), json(r#"[true, -150, "\u00e9"]"#));
+    }
+
+    #[test]
+    fn cut_key_is_not_shown() {
+        let mut parser = IncrementalParser::new();
+        parser.feed(r#"{"done": 1, "ke"#).unwrap();
+        let snapshot = parser.snapshot();
+        assert_eq!(snapshot.value, Some(json(r#"{"done": 1}"#)));
+        assert_eq!(snapshot.incomplete, vec![path(&[])]);
+    }
+
+    #[test]
+    fn one_byte_at_a_time() {
+        let input = "{\n  \"list\": [1, 22, 333, \"s\\\"\", null, false],\n  \"e\": {}\n}";
+        let mut parser = IncrementalParser::new();
+        for c in input.chars() {
+            parser.feed(&c.to_string()).unwrap();
+            // Any prefix gives a value
+            parser.snapshot();
+        }
+        assert_eq!(parser.finish().unwrap(), json(input));
+    }
+
+    #[test]
+    fn strings_are_read_once() {
+        let content = "abc\\u00e9\\ud83d\\udc31\\n".repeat(20);
+        let input = format!("[\"{}\"]", content);
+        let mut parser = IncrementalParser::new();
+        let mut shown = 0;
+        for (idx, c) in input.char_indices() {
+            parser.feed(&c.to_string()).unwrap();
+            // Only a cut escape is kept for the next chunk
+            assert!(parser.buffer.len() <= 11);
+            if let Some(Json::Array(vec)) = parser.snapshot().value {
+                if let Some(Json::String(text)) = vec.first() {
+                    assert!(text.chars().count() >= shown);
+                    shown = text.chars().count();
+                    assert!(!text.contains('\u{FFFD}'), "{:?} at {}", text, idx);
+                }
+            }
+        }
+        assert_eq!(parser.finish().unwrap(), json(&input));
+    }
+
+    #[test]
+    fn errors_in_strings_of_several_chunks() {
+        let mut parser = IncrementalParser::new();
+        parser.feed("{\"a\": 1,\n \"lo").unwrap();
+        parser.feed("ng\" 2}").unwrap_err();
+        let mut parser = IncrementalParser::new();
+        parser.feed("{\"a\": 1 \"lo").unwrap();
+        let error = parser.feed("ng\": 2}").unwrap_err();
+        assert_eq!(
+            (error.kind, error.context.column, error.context.offset),
+            (
+                JsonErrorKind::UnexpectedToken {
+                    found: TokenKind::ValueString,
+                    expected: crate::grammar::OBJECT_NEXT.to_vec()
+                },
+                9,
+                8
+            )
+        );
+        let mut parser = IncrementalParser::new();
+        parser.feed("[\"unterminated").unwrap();
+        assert_eq!(
+            parser.finish().unwrap_err().kind,
+            JsonErrorKind::UnterminatedString
+        );
+    }
+
+    #[test]
+    fn limits() {
+        let limits = ParseLimits {
+            max_string_length: Some(4),
+            ..ParseLimits::default()
+        };
+        let mut parser = IncrementalParser::with_limits(limits);
+        parser.feed("[\"ab").unwrap();
+        let error = parser.feed("cde\"]").unwrap_err();
+        assert_eq!(
+            (error.kind, error.context.offset),
+            (JsonErrorKind::LimitExceeded(Limit::StringLength(4)), 1)
+        );
+        let limits = ParseLimits {
+            max_input_bytes: Some(8),
+            ..ParseLimits::default()
+        };
+        let mut parser = IncrementalParser::with_limits(limits);
+        parser.feed("[1, 2, ").unwrap();
+        assert_eq!(
+            parser.feed("3]").unwrap_err().kind,
+            JsonErrorKind::LimitExceeded(Limit::InputBytes(8))
+        );
+    }
+
+    #[test]
+    fn errors_have_document_positions() {
+        let mut parser = IncrementalParser::new();
+        parser.feed("[1,\n 2").unwrap();
+        let error = parser.feed(" 3]").unwrap_err();
+        assert_eq!((error.context.line, error.context.column), (2, 4));
+        assert_eq!(error.context.offset, 7);
+        // The parser stays in error
+        assert_eq!(parser.feed("]"), Err(error.clone()));
+        assert_eq!(parser.finish(), Err(error));
+    }
+
+    #[test]
+    fn trailing_tokens_are_errors() {
+        let mut parser = IncrementalParser::new();
+        parser.feed("[1] ").unwrap();
+        assert_eq!(
+            parser.feed("\n2 ").unwrap_err(),
+            JsonError::new(
+                JsonErrorKind::TrailingToken(TokenKind::ValueNumber),
+                Context {
+                    line: 2,
+                    column: 1,
+                    offset: 5
+                }
+            )
+        );
+        let mut parser = IncrementalParser::new();
+        parser.feed("1 2").unwrap();
+        assert_eq!(
+            parser.finish().unwrap_err().kind,
+            JsonErrorKind::TrailingToken(TokenKind::ValueNumber)
+        );
+    }
+
+    #[test]
+    fn finish_checks_the_end() {
+        let mut parser = IncrementalParser::new();
+        parser.feed("[1, 2").unwrap();
+        assert_eq!(
+            parser.finish().unwrap_err().kind,
+            JsonErrorKind::UnexpectedEof {
+                expected: ARRAY_NEXT.to_vec()
+            }
+        );
+        let mut parser = IncrementalParser::new();
+        parser.feed("12").unwrap();
+        assert_eq!(parser.finish(), Ok(Json::Number(12.)));
+        let mut parser = IncrementalParser::new();
+        parser.feed("[-").unwrap();
+        assert_eq!(
+            parser.finish().unwrap_err().kind,
+            JsonErrorKind::InvalidNumber(String::from("-"))
+        );
+        assert_eq!(
+            IncrementalParser::new().finish().unwrap_err().kind,
+            JsonErrorKind::EmptyInput
+        );
+    }
+}
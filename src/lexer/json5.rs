@@ -0,0 +1,469 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! The tokens which only exist in JSON5, or which it reads differently:
+//! single quoted strings, identifiers, extended numbers, escapes and
+//! whitespace. See <https://spec.json5.org>.
+
+use super::{string_to_unicode_char, unicode, Escaped, Lexer, LexerResult, Token};
+use crate::{JsonError, JsonErrorKind, Limit};
+use std::convert::TryFrom;
+use std::str;
+use std::str::FromStr;
+
+/// A byte starting a token which JSON5 reads differently than JSON.
+pub(super) fn is_token_start(b: u8) -> bool {
+    matches!(
+        b,
+        b'\'' | b'+' | b'-' | b'.' | b'0'..=b'9' | b'$' | b'_' | b'\\' | b'a'..=b'z' | b'A'..=b'Z'
+    ) || b >= 0x80
+}
+
+/// The whitespace of ECMAScript 5.1 which JSON does not have.
+fn is_whitespace(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0B}' | '\u{0C}' | '\u{A0}' | '\u{FEFF}' | '\u{1680}' | '\u{2000}'
+            ..='\u{200A}' | '\u{2028}' | '\u{2029}' | '\u{202F}' | '\u{205F}' | '\u{3000}'
+    )
+}
+
+/// An IdentifierStart of ECMAScript 5.1, without the `\u` escapes.
+pub(crate) fn is_identifier_start(c: char) -> bool {
+    c == '$' || c == '_' || unicode::is_letter(c)
+}
+
+/// An IdentifierPart of ECMAScript 5.1, without the `\u` escapes.
+pub(crate) fn is_identifier_part(c: char) -> bool {
+    // Zero width non-joiner and joiner
+    is_identifier_start(c)
+        || unicode::is_mark_digit_or_connector(c)
+        || c == '\u{200C}'
+        || c == '\u{200D}'
+}
+
+/// The nearest `f64` of hexadecimal `digits`. The first 32 significant digits
+/// are read as an integer, rounded once, and the following ones can only
+/// break a tie, so they are kept as a sticky bit below it.
+fn hex_to_f64(digits: &str) -> f64 {
+    let digits = digits.trim_start_matches('0');
+    if digits.len() <= 32 {
+        return u128::from_str_radix(digits, 16).map_or(0., |value| value as f64);
+    }
+    let (head, tail) = digits.split_at(32);
+    let mut value = u128::from_str_radix(head, 16).unwrap();
+    if tail.bytes().any(|b| b != b'0') {
+        value |= 1;
+    }
+    match i32::try_from(4 * tail.len()) {
+        Ok(exponent) if exponent < 1024 => value as f64 * 2f64.powi(exponent),
+        _ => f64::INFINITY,
+    }
+}
+
+impl<'a> Lexer<'a> {
+    pub(super) fn consume_json5_token(&mut self, first: u8) -> LexerResult {
+        match first {
+            b'\'' => self.consume_string(b'\''),
+            b'+' | b'-' | b'.' | b'0'..=b'9' => self.consume_json5_number(),
+            _ => self.consume_identifier(),
+        }
+    }
+
+    /// Skip one JSON5 only whitespace character, returning `false` if there
+    /// is none.
+    pub(super) fn consume_json5_whitespace(&mut self) -> bool {
+        match self.peek_char() {
+            Some(c) if is_whitespace(c) => {
+                self.consume_slice(c.len_utf8());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Read an identifier, the literals being identifiers too.
+    fn consume_identifier(&mut self) -> LexerResult {
+        let mut name = String::new();
+        loop {
+            let c = match self.peek_char_checked()? {
+                Some('\\') => self.consume_identifier_escape(name.is_empty())?,
+                Some(c) if name.is_empty() && is_identifier_start(c) => {
+                    self.consume_slice(c.len_utf8());
+                    c
+                }
+                Some(c) if !name.is_empty() && is_identifier_part(c) => {
+                    self.consume_slice(c.len_utf8());
+                    c
+                }
+                _ if name.is_empty() => return self.consume_unexpected_character(),
+                _ => break,
+            };
+            name.push(c);
+            self.check_string_length(name.len())?;
+        }
+        let token = match name.as_str() {
+            "true" => Token::ValueBoolean(true),
+            "false" => Token::ValueBoolean(false),
+            "null" => Token::ValueNull,
+            "Infinity" => Token::ValueNumber(f64::INFINITY),
+            "NaN" => Token::ValueNumber(f64::NAN),
+            _ => Token::Identifier(name),
+        };
+        Ok(self.build_result(token))
+    }
+
+    /// Read a `\uXXXX` escape in an identifier.
+    fn consume_identifier_escape(&mut self, first: bool) -> Result<char, JsonError> {
+        self.consume_byte();
+        match self.peek_char_checked()? {
+            Some('u') => self.consume_byte(),
+            Some(c) => return Err(self.build_error(JsonErrorKind::InvalidEscape(c))),
+            None => {
+                return Err(self.build_error(JsonErrorKind::UnexpectedEof {
+                    expected: Vec::new(),
+                }))
+            }
+        };
+        let digits = self.consume_n_times(4)?;
+        match string_to_unicode_char(digits) {
+            Some(c) if first && is_identifier_start(c) || !first && is_identifier_part(c) => Ok(c),
+            _ => Err(self.build_error(JsonErrorKind::InvalidUnicodeEscape(digits.to_string()))),
+        }
+    }
+
+    /// Read a number with an optional sign, which can be hexadecimal,
+    /// `Infinity`, `NaN`, or start or end with a decimal point.
+    fn consume_json5_number(&mut self) -> LexerResult {
+        let start = self.pos;
+        let negative = match self.peek_byte() {
+            Some(b'-') => {
+                self.consume_byte();
+                true
+            }
+            Some(b'+') => {
+                self.consume_byte();
+                false
+            }
+            _ => false,
+        };
+        let value = match self.peek_byte() {
+            Some(b'I') => {
+                self.consume_seq(b"Infinity")?;
+                f64::INFINITY
+            }
+            Some(b'N') => {
+                self.consume_seq(b"NaN")?;
+                f64::NAN
+            }
+            Some(b'0') if matches!(self.data.get(self.pos + 1), Some(b'x' | b'X')) => {
+                self.consume_slice(2);
+                let digits = self.consume_digits(u8::is_ascii_hexdigit);
+                self.check_number(start, digits, u8::is_ascii_hexdigit)?;
+                // Only ASCII hexadecimal digits
+                hex_to_f64(str::from_utf8(&self.data[self.pos - digits..self.pos]).unwrap())
+            }
+            _ => {
+                let mut digits = match self.peek_byte() {
+                    // No leading zero, the next digits are another token
+                    Some(b'0') => {
+                        self.consume_byte();
+                        1
+                    }
+                    _ => self.consume_digits(u8::is_ascii_digit),
+                };
+                if self.peek_byte() == Some(b'.') {
+                    self.consume_byte();
+                    digits += self.consume_digits(u8::is_ascii_digit);
+                }
+                if digits > 0 && matches!(self.peek_byte(), Some(b'e' | b'E')) {
+                    self.consume_byte();
+                    if let Some(b'+' | b'-') = self.peek_byte() {
+                        self.consume_byte();
+                    }
+                    if self.consume_digits(u8::is_ascii_digit) == 0 {
+                        digits = 0;
+                    }
+                }
+                self.check_number(start, digits, u8::is_ascii_digit)?;
+                // Only ASCII bytes were consumed
+                let number = str::from_utf8(&self.data[start..self.pos]).unwrap();
+                let number = number.trim_start_matches(['+', '-']);
+                f64::from_str(number).map_err(|_| {
+                    self.build_error(JsonErrorKind::InvalidNumber(number.to_string()))
+                })?
+            }
+        };
+        let value = if negative { -value } else { value };
+        Ok(self.build_result(Token::ValueNumber(value)))
+    }
+
+    /// Consume the digits matching `is_digit`, returning how many there were.
+    fn consume_digits(&mut self, is_digit: fn(&u8) -> bool) -> usize {
+        let count = self.data[self.pos..]
+            .iter()
+            .take_while(|b| is_digit(b))
+            .count();
+        self.consume_slice(count);
+        count
+    }
+
+    /// Check the number read from `start` has digits, and not too many of
+    /// them.
+    fn check_number(
+        &self,
+        start: usize,
+        digits: usize,
+        is_digit: fn(&u8) -> bool,
+    ) -> Result<(), JsonError> {
+        if digits == 0 {
+            // Only ASCII bytes were consumed
+            let number = str::from_utf8(&self.data[start..self.pos]).unwrap();
+            return Err(self.build_error(JsonErrorKind::InvalidNumber(number.to_string())));
+        }
+        if let Some(max) = self.limits.max_number_digits {
+            let digits = self.data[start..self.pos]
+                .iter()
+                .filter(|b| is_digit(b))
+                .count();
+            if digits > max {
+                return Err(self.build_limit_error(Limit::NumberDigits(max)));
+            }
+        }
+        Ok(())
+    }
+
+    /// The escapes JSON5 adds to JSON, the first byte of `c` being consumed.
+    pub(super) fn consume_json5_escape(&mut self, c: char) -> Result<Escaped, JsonError> {
+        self.consume_slice(c.len_utf8() - 1);
+        let escaped = match c {
+            '\'' => '\'',
+            'v' => '\u{0B}',
+            '0' if !matches!(self.peek_byte(), Some(b'0'..=b'9')) => '\0',
+            'x' => {
+                let digits = self.consume_n_times(2)?;
+                char::from(u8::from_str_radix(digits, 16).unwrap())
+            }
+            '\r' => {
+                if self.peek_byte() == Some(b'\n') {
+                    self.consume_byte();
+                }
+                return Ok(Escaped::LineContinuation);
+            }
+            '\n' | '\u{2028}' | '\u{2029}' => return Ok(Escaped::LineContinuation),
+            '0'..='9' => return Err(self.build_error(JsonErrorKind::InvalidEscape(c))),
+            c => c,
+        };
+        Ok(Escaped::Char(escaped))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_identifier_part, is_identifier_start};
+    use crate::lexer::Token;
+    use crate::{parse_json_with_dialect, Dialect, Json, JsonErrorKind, Lexer};
+
+    fn parse(input: &str) -> Result<Json, JsonErrorKind> {
+        parse_json_with_dialect(input, Dialect::Json5).map_err(|e| e.kind)
+    }
+
+    fn tokens(input: &str) -> Vec<Token> {
+        let mut lexer = Lexer::new(input);
+        lexer.set_dialect(Dialect::Json5);
+        lexer.map(|result| result.unwrap().token).collect()
+    }
+
+    fn number(input: &str) -> f64 {
+        match parse(input) {
+            Ok(Json::Number(n)) => n,
+            other => panic!("{:?} is not a number: {:?}", input, other),
+        }
+    }
+
+    #[test]
+    fn number_values() {
+        let cases: &[(&str, f64)] = &[
+            ("-0", -0.),
+            ("+1", 1.),
+            ("1.", 1.),
+            (".5", 0.5),
+            ("-.5e1", -5.),
+            ("+.5E-1", 0.05),
+            ("0x1F", 31.),
+            ("-0XfF", -255.),
+            ("0x1FFFFFFFFFFFFF1", 144115188075855856.),
+            // Rounded once, not at every digit
+            ("0x2000000000000101", 2305843009213694464.),
+            // A tie broken by a digit after the first 32 ones
+            (
+                "0x2000000000000100000000000000000000000000000000000000001",
+                2.106245833371144e65,
+            ),
+            (
+                "0x2000000000000100000000000000000000000000000000000000000",
+                2.1062458333711437e65,
+            ),
+            ("-Infinity", f64::NEG_INFINITY),
+        ];
+        for &(input, expected) in cases {
+            assert_eq!(number(input), expected, "{}", input);
+        }
+        assert!(number("-NaN").is_nan());
+        assert!(number("0.0").is_sign_positive());
+        assert!(number("-0").is_sign_negative());
+    }
+
+    #[test]
+    fn number_errors() {
+        assert_eq!(
+            parse("0x"),
+            Err(JsonErrorKind::InvalidNumber(String::from("0x")))
+        );
+        assert_eq!(
+            parse("-Inf"),
+            Err(JsonErrorKind::UnexpectedEof {
+                expected: Vec::new()
+            })
+        );
+    }
+
+    #[test]
+    fn strings() {
+        let cases = &[
+            (r#"'single'"#, "single"),
+            (r#"'with "double" quotes'"#, "with \"double\" quotes"),
+            (r#""with 'single' quotes""#, "with 'single' quotes"),
+            (r#"'escaped \' quote'"#, "escaped ' quote"),
+            (r#"'\v\0\x41\q'"#, "\u{0B}\0Aq"),
+            ("'line \\\ncontinued'", "line continued"),
+            ("'line \\\r\ncontinued'", "line continued"),
+            ("'line \\\u{2028}continued'", "line continued"),
+            ("'\u{2028}\u{2029}'", "\u{2028}\u{2029}"),
+            ("'tab\tinside'", "tab\tinside"),
+            ("'\\é'", "é"),
+        ];
+        for &(input, expected) in cases {
+            assert_eq!(
+                parse(input),
+                Ok(Json::String(expected.to_string())),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_strings() {
+        assert_eq!(parse(r#"'\01'"#), Err(JsonErrorKind::InvalidEscape('0')));
+        assert_eq!(parse(r#"'\1'"#), Err(JsonErrorKind::InvalidEscape('1')));
+        assert_eq!(
+            parse(r#"'\x4'"#),
+            Err(JsonErrorKind::InvalidUnicodeEscape(String::from("4'")))
+        );
+        assert_eq!(
+            parse("'a\nb'"),
+            Err(JsonErrorKind::ControlCharacterInString('\n'))
+        );
+        assert_eq!(
+            parse("'unterminated"),
+            Err(JsonErrorKind::UnterminatedString)
+        );
+        assert_eq!(parse("'mixed\""), Err(JsonErrorKind::UnterminatedString));
+    }
+
+    #[test]
+    fn identifiers() {
+        assert_eq!(
+            tokens("\\u0061e ünï a\u{301} a\u{203F}b null Infinity"),
+            vec![
+                Token::Identifier(String::from("ae")),
+                Token::Identifier(String::from("ünï")),
+                Token::Identifier(String::from("a\u{301}")),
+                Token::Identifier(String::from("a\u{203F}b")),
+                Token::ValueNull,
+                Token::ValueNumber(f64::INFINITY),
+            ]
+        );
+        assert_eq!(
+            parse(r"{\u0030a: 1}"),
+            Err(JsonErrorKind::InvalidUnicodeEscape(String::from("0030")))
+        );
+        assert_eq!(parse(r"{a\b: 1}"), Err(JsonErrorKind::InvalidEscape('b')));
+        assert_eq!(
+            parse("{@: 1}"),
+            Err(JsonErrorKind::UnexpectedCharacter {
+                found: '@',
+                expected: None
+            })
+        );
+    }
+
+    #[test]
+    fn identifier_categories() {
+        // Lt, Lm, Lo and Nl letters
+        for &c in &['\u{1C5}', '\u{2B0}', '\u{5D0}', '\u{216B}'] {
+            assert!(is_identifier_start(c), "{:?}", c);
+        }
+        // Mn, Mc, Nd and Pc, and the zero width joiners
+        for &c in &[
+            '\u{301}', '\u{903}', '\u{663}', '\u{203F}', '\u{200C}', '\u{200D}',
+        ] {
+            assert!(!is_identifier_start(c), "{:?}", c);
+            assert!(is_identifier_part(c), "{:?}", c);
+        }
+        // Alphabetic or numeric, but in none of the categories
+        for &c in &['\u{24B6}', '\u{B2}', '\u{BD}'] {
+            assert!(!is_identifier_part(c), "{:?}", c);
+        }
+        assert!(parse("{\u{345}a: 1}").is_err());
+        assert!(parse("{a\u{345}: 1}").is_ok());
+    }
+
+    #[test]
+    fn whitespace() {
+        let input = "\u{0B}\u{0C}\u{A0}\u{FEFF}\u{2003}\u{2028}\u{3000}[1]\u{2029}";
+        assert_eq!(parse(input), Ok(Json::Array(vec![Json::Number(1.)])));
+        assert!(parse("\u{200B}1").is_err());
+        assert_eq!(parse("// comment\u{2028}1"), Ok(Json::Number(1.)));
+    }
+
+    #[test]
+    fn objects() {
+        let input = "// Comments\n{unquoted: 'a', 'single': 2, \"double\": [3,],\n\
+                     null: null, true: /* keywords */ false, Infinity: NaN, }";
+        let map = match parse(input) {
+            Ok(Json::Object(map)) => map,
+            other => panic!("Not an object: {:?}", other),
+        };
+        assert_eq!(map["unquoted"], Json::String(String::from("a")));
+        assert_eq!(map["single"], Json::Number(2.));
+        assert_eq!(map["double"], Json::Array(vec![Json::Number(3.)]));
+        assert_eq!(map["null"], Json::Null);
+        assert_eq!(map["true"], Json::Boolean(false));
+        assert!(matches!(map["Infinity"], Json::Number(n) if n.is_nan()));
+        assert!(parse("{-Infinity: 1}").is_err());
+        assert!(parse("{1: 1}").is_err());
+        assert!(parse("{a b: 1}").is_err());
+        assert!(parse("[a]").is_err());
+    }
+}
@@ -20,47 +20,216 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
 
+mod cst;
+mod diagnostic;
+mod dialect;
+mod encoding;
+mod grammar;
+mod highlight;
+mod incremental;
 mod lexer;
+mod limits;
+mod number;
 mod parser;
+mod position;
+mod recovery;
+mod repair;
+mod serializer;
+mod spanned;
+mod structural;
+mod validate;
+mod wtf8;
 
-pub use lexer::Lexer;
-pub use parser::parse_json;
+pub use cst::{
+    CstArray, CstDocument, CstElement, CstMember, CstObject, CstScalar, CstToken, CstValue,
+};
+pub use diagnostic::{render_diagnostic, render_diagnostic_slice, DiagnosticOptions};
+pub use dialect::Dialect;
+pub use encoding::{detect_encoding, BomPolicy, Encoding};
+pub use highlight::{
+    highlight, render_highlights_ansi, render_highlights_html, Highlight, HighlightKind,
+};
+pub use incremental::{IncrementalParser, Snapshot};
+pub use lexer::{Checkpoint, Lexer, LexerResult, NumberHandler, Token, TokenInfo, TokenKind};
+pub use limits::{Limit, ParseLimits};
+pub use number::{CustomNumber, NumberError, NumberLexeme, NumberValue};
+pub use parser::{parse_json, parse_json_with_dialect, parse_json_with_limits};
+pub use parser::{parse_slice, parse_slice_with_options};
 pub use parser::{Json, Parser};
+pub use position::{ColumnEncoding, LineIndex};
+pub use recovery::{parse_json_recovering, parse_json_recovering_with_limits, Recovered};
+pub use repair::{repair_json, repair_json_with_limits, Repair, RepairKind};
+pub use serializer::Json5Display;
+pub use spanned::{parse_json_spanned, parse_json_spanned_with_limits, Span, Spanned, SpannedJson};
 use std::fmt;
+pub use structural::{Implementation, StructuralIndex};
+pub use validate::{validate, validate_with_limits};
+pub use wtf8::{CodePoints, SurrogatePolicy, Wtf8String};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Context {
     pub line: usize,
     pub column: usize,
+    /// Byte offset in the input
+    pub offset: usize,
 }
 
 impl Default for Context {
     fn default() -> Self {
-        Self { line: 1, column: 1 }
+        Self {
+            line: 1,
+            column: 1,
+            offset: 0,
+        }
     }
 }
 
-#[derive(Debug)]
-pub enum JsonError {
-    Lexer { context: Context, message: String },
-    Parser { context: Context, message: String },
-    Other(String),
+#[derive(Clone, Debug, PartialEq)]
+pub enum JsonErrorKind {
+    /// The input has no token at all
+    EmptyInput,
+    /// The input ended while more was expected, `expected` is empty when the
+    /// end happened inside a token
+    UnexpectedEof {
+        expected: Vec<TokenKind>,
+    },
+    UnterminatedString,
+    /// A `/*` comment without its `*/`, only with the dialects allowing
+    /// comments
+    UnterminatedComment,
+    /// A character which cannot start a token, or which does not match the
+    /// literal or escape being read
+    UnexpectedCharacter {
+        found: char,
+        expected: Option<char>,
+    },
+    InvalidEscape(char),
+    InvalidUnicodeEscape(String),
+    ControlCharacterInString(char),
+    InvalidNumber(String),
+    /// A number refused by the number handler, see `Lexer::set_number_handler`
+    RejectedNumber(String),
+    /// Bytes which are not valid UTF-8, only possible with byte slice input
+    InvalidUtf8(Vec<u8>),
+    /// Bytes which are not valid in the detected UTF-16 or UTF-32 encoding
+    InvalidEncoding(Encoding, Vec<u8>),
+    UnexpectedToken {
+        found: TokenKind,
+        expected: Vec<TokenKind>,
+    },
+    LimitExceeded(Limit),
+    /// A token after the end of the document, which must be a single value
+    TrailingToken(TokenKind),
 }
 
-impl fmt::Display for JsonError {
+impl JsonErrorKind {
+    fn origin(&self) -> &'static str {
+        match self {
+            JsonErrorKind::EmptyInput
+            | JsonErrorKind::UnexpectedEof { .. }
+            | JsonErrorKind::UnexpectedToken { .. }
+            | JsonErrorKind::TrailingToken(_) => "Parser",
+            JsonErrorKind::LimitExceeded(_) => "Limit",
+            _ => "Lexer",
+        }
+    }
+}
+
+fn write_expected(f: &mut fmt::Formatter<'_>, expected: &[TokenKind]) -> fmt::Result {
+    for (idx, token) in expected.iter().enumerate() {
+        match idx {
+            0 => write!(f, "{}", token)?,
+            _ if idx == expected.len() - 1 => write!(f, " or {}", token)?,
+            _ => write!(f, ", {}", token)?,
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for JsonErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            JsonError::Lexer { context, message } => write!(
+            JsonErrorKind::EmptyInput => write!(f, "No data to parse"),
+            JsonErrorKind::UnexpectedEof { expected } if expected.is_empty() => {
+                write!(f, "End of stream while waiting for more characters")
+            }
+            JsonErrorKind::UnexpectedEof { expected } => {
+                write!(f, "End of stream while waiting ")?;
+                write_expected(f, expected)
+            }
+            JsonErrorKind::UnterminatedString => {
+                write!(f, "EOF encountered while recognizing a string")
+            }
+            JsonErrorKind::UnterminatedComment => {
+                write!(f, "EOF encountered while recognizing a comment")
+            }
+            JsonErrorKind::UnexpectedCharacter {
+                found,
+                expected: None,
+            } => write!(f, "The character '{}' is unexpected", found),
+            JsonErrorKind::UnexpectedCharacter {
+                found,
+                expected: Some(expected),
+            } => write!(
                 f,
-                "Lexer error, line {} column {}: {}",
-                context.line, context.column, message
+                "Unexpected char '{}', was waiting for a '{}'",
+                found, expected
             ),
-            JsonError::Parser { context, message } => write!(
+            JsonErrorKind::InvalidEscape(c) => write!(f, "'{}' is not an escapable character", c),
+            JsonErrorKind::InvalidUnicodeEscape(value) => {
+                write!(f, "'{}' is not a valid unicode escape", value)
+            }
+            JsonErrorKind::ControlCharacterInString(c) => write!(
                 f,
-                "Parser error, line {} column {}: {}",
-                context.line, context.column, message
+                "The control character {:?} must be escaped in a string",
+                c
             ),
-            JsonError::Other(message) => write!(f, "Other error: {}", message),
+            JsonErrorKind::InvalidNumber(number) => {
+                write!(f, "Could not convert '{}' to a number", number)
+            }
+            JsonErrorKind::RejectedNumber(message) => write!(f, "Rejected number: {}", message),
+            JsonErrorKind::InvalidUtf8(bytes) => {
+                write!(f, "Invalid UTF-8 sequence {:02X?}", bytes)
+            }
+            JsonErrorKind::InvalidEncoding(encoding, bytes) => {
+                write!(f, "Invalid {} sequence {:02X?}", encoding, bytes)
+            }
+            JsonErrorKind::UnexpectedToken { found, expected } => {
+                write!(f, "Was waiting ")?;
+                write_expected(f, expected)?;
+                write!(f, " but received {}", found)
+            }
+            JsonErrorKind::LimitExceeded(limit) => write!(f, "{}", limit),
+            JsonErrorKind::TrailingToken(found) => {
+                write!(f, "Was waiting the end of the input but received {}", found)
+            }
         }
     }
 }
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct JsonError {
+    pub kind: JsonErrorKind,
+    pub context: Context,
+}
+
+impl JsonError {
+    pub fn new(kind: JsonErrorKind, context: Context) -> Self {
+        JsonError { kind, context }
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} error, line {} column {}: {}",
+            self.kind.origin(),
+            self.context.line,
+            self.context.column,
+            self.kind
+        )
+    }
+}
+
+impl std::error::Error for JsonError {}
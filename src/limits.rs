@@ -0,0 +1,100 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+use std::fmt;
+
+/// Resource limits applied while parsing, `None` meaning unlimited.
+///
+/// The default profile is meant for servers parsing untrusted input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseLimits {
+    /// Number of nested arrays and objects
+    pub max_depth: Option<usize>,
+    /// Size of the whole input
+    pub max_input_bytes: Option<usize>,
+    /// Size in bytes of a string once unescaped, keys included
+    pub max_string_length: Option<usize>,
+    /// Number of elements in a single array or object
+    pub max_container_elements: Option<usize>,
+    /// Number of values in the whole document, arrays and objects included
+    pub max_total_values: Option<usize>,
+    /// Number of digits of a number, exponent included
+    pub max_number_digits: Option<usize>,
+}
+
+impl ParseLimits {
+    pub fn unlimited() -> Self {
+        ParseLimits {
+            max_depth: None,
+            max_input_bytes: None,
+            max_string_length: None,
+            max_container_elements: None,
+            max_total_values: None,
+            max_number_digits: None,
+        }
+    }
+}
+
+impl Default for ParseLimits {
+    fn default() -> Self {
+        ParseLimits {
+            max_depth: Some(128),
+            max_input_bytes: Some(64 * 1024 * 1024),
+            max_string_length: Some(8 * 1024 * 1024),
+            max_container_elements: Some(1_000_000),
+            max_total_values: Some(10_000_000),
+            max_number_digits: Some(100),
+        }
+    }
+}
+
+/// The limit which was exceeded, with its configured value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Limit {
+    Depth(usize),
+    InputBytes(usize),
+    StringLength(usize),
+    ContainerElements(usize),
+    TotalValues(usize),
+    NumberDigits(usize),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Depth(max) => write!(f, "maximum nesting depth of {} exceeded", max),
+            Limit::InputBytes(max) => write!(f, "maximum input size of {} bytes exceeded", max),
+            Limit::StringLength(max) => {
+                write!(f, "maximum string length of {} bytes exceeded", max)
+            }
+            Limit::ContainerElements(max) => {
+                write!(
+                    f,
+                    "maximum number of {} elements in a container exceeded",
+                    max
+                )
+            }
+            Limit::TotalValues(max) => write!(f, "maximum number of {} values exceeded", max),
+            Limit::NumberDigits(max) => write!(f, "maximum number of {} digits exceeded", max),
+        }
+    }
+}
@@ -0,0 +1,433 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Decimal accumulator for the lexer, with Clinger's fast path to convert the
+//! usual numbers to `f64` without going through a string.
+//!
+//! Also the types of the number handler hook, see `Lexer::set_number_handler`.
+
+use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
+
+/// A number as written in the input, split in its parts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NumberLexeme<'a> {
+    /// The whole number, such as `-12.50e+3`
+    pub text: &'a str,
+    pub negative: bool,
+    /// The digits before the dot, such as `12`
+    pub integer: &'a str,
+    /// The digits after the dot, such as `50`
+    pub fraction: Option<&'a str>,
+    /// The exponent with its sign if any, such as `+3`
+    pub exponent: Option<&'a str>,
+}
+
+impl<'a> NumberLexeme<'a> {
+    /// Split a number already validated against the JSON grammar.
+    pub(crate) fn new(text: &'a str) -> Self {
+        let negative = text.starts_with('-');
+        let unsigned = if negative { &text[1..] } else { text };
+        let (mantissa, exponent) = match unsigned.find(['e', 'E']) {
+            Some(idx) => (&unsigned[..idx], Some(&unsigned[idx + 1..])),
+            None => (unsigned, None),
+        };
+        let (integer, fraction) = match mantissa.find('.') {
+            Some(idx) => (&mantissa[..idx], Some(&mantissa[idx + 1..])),
+            None => (mantissa, None),
+        };
+        NumberLexeme {
+            text,
+            negative,
+            integer,
+            fraction,
+            exponent,
+        }
+    }
+}
+
+/// The value a number handler gives to a number.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NumberValue {
+    /// Read as a usual `Token::ValueNumber`
+    Float(f64),
+    /// Read as a `Token::ValueCustomNumber`
+    Custom(CustomNumber),
+}
+
+/// Rejection of a number by a number handler.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NumberError {
+    pub message: String,
+    /// Byte offset of the faulty part in the lexeme text
+    pub offset: usize,
+}
+
+impl NumberError {
+    pub fn new(message: impl Into<String>, offset: usize) -> Self {
+        NumberError {
+            message: message.into(),
+            offset,
+        }
+    }
+}
+
+/// A value of any type made by a number handler, with the text it was read
+/// from, which is a JSON number. Two custom numbers are equal when their texts
+/// are, whatever their values.
+#[derive(Clone)]
+pub struct CustomNumber {
+    value: Arc<dyn Any + Send + Sync>,
+    text: String,
+}
+
+impl CustomNumber {
+    /// A custom number written as `text`, which must follow the JSON number
+    /// grammar since the serializer writes it as is. The error has the offset
+    /// of the first byte which does not.
+    pub fn new<T: Any + Send + Sync>(
+        value: T,
+        text: impl Into<String>,
+    ) -> Result<Self, NumberError> {
+        let text = text.into();
+        if let Some(offset) = json_number_error(&text) {
+            return Err(NumberError::new("not a JSON number", offset));
+        }
+        Ok(CustomNumber {
+            value: Arc::new(value),
+            text,
+        })
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.value.downcast_ref()
+    }
+
+    /// The text of the number, written back by the serializer.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+impl fmt::Debug for CustomNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CustomNumber").field(&self.text).finish()
+    }
+}
+
+impl PartialEq for CustomNumber {
+    /// The values cannot be compared, their type being erased.
+    fn eq(&self, other: &Self) -> bool {
+        self.text == other.text
+    }
+}
+
+/// Offset of the first byte of `text` which breaks the JSON number grammar,
+/// the length of `text` if it ends too early, or `None` for a valid number.
+fn json_number_error(text: &str) -> Option<usize> {
+    let bytes = text.as_bytes();
+    let digits = |pos: usize| {
+        bytes[pos..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+    };
+    let mut pos = usize::from(bytes.first() == Some(&b'-'));
+    match bytes.get(pos) {
+        Some(b'0') => pos += 1,
+        Some(b'1'..=b'9') => pos += digits(pos),
+        _ => return Some(pos),
+    }
+    if bytes.get(pos) == Some(&b'.') {
+        pos += 1;
+        match digits(pos) {
+            0 => return Some(pos),
+            count => pos += count,
+        }
+    }
+    if let Some(b'e' | b'E') = bytes.get(pos) {
+        pos += 1;
+        if let Some(b'+' | b'-') = bytes.get(pos) {
+            pos += 1;
+        }
+        match digits(pos) {
+            0 => return Some(pos),
+            count => pos += count,
+        }
+    }
+    if pos < bytes.len() {
+        Some(pos)
+    } else {
+        None
+    }
+}
+
+/// Largest integer such that every integer up to it is exactly representable.
+const MAX_EXACT_INT: u64 = 1 << 53;
+
+/// Powers of ten which are exactly representable as `f64`.
+const EXACT_POWERS_OF_TEN: [f64; 23] = [
+    1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16,
+    1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+];
+
+/// Decimal number `mantissa * 10^exponent` built digit by digit.
+#[derive(Debug, Default)]
+pub(crate) struct Decimal {
+    negative: bool,
+    mantissa: u64,
+    /// Some significant digits did not fit in the mantissa
+    truncated: bool,
+    /// Shift due to the digits of the fractional part
+    fraction_digits: i64,
+    exponent_negative: bool,
+    exponent: i64,
+}
+
+impl Decimal {
+    pub fn set_negative(&mut self) {
+        self.negative = true;
+    }
+
+    pub fn push_int_digit(&mut self, digit: u8) {
+        self.push_mantissa_digit(digit);
+    }
+
+    pub fn push_fraction_digit(&mut self, digit: u8) {
+        if self.push_mantissa_digit(digit) {
+            self.fraction_digits += 1;
+        }
+    }
+
+    pub fn set_exponent_negative(&mut self) {
+        self.exponent_negative = true;
+    }
+
+    pub fn push_exponent_digit(&mut self, digit: u8) {
+        // Saturate, such an exponent is anyway out of the range of f64
+        self.exponent = self
+            .exponent
+            .saturating_mul(10)
+            .saturating_add(i64::from(digit - b'0'));
+    }
+
+    /// Add a digit to the mantissa, return `false` if it had to be dropped.
+    fn push_mantissa_digit(&mut self, digit: u8) -> bool {
+        let value = u64::from(digit - b'0');
+        match self
+            .mantissa
+            .checked_mul(10)
+            .and_then(|m| m.checked_add(value))
+        {
+            Some(mantissa) => {
+                self.mantissa = mantissa;
+                true
+            }
+            None => {
+                self.truncated |= value != 0;
+                false
+            }
+        }
+    }
+
+    fn exponent(&self) -> i64 {
+        let exponent = if self.exponent_negative {
+            -self.exponent
+        } else {
+            self.exponent
+        };
+        exponent.saturating_sub(self.fraction_digits)
+    }
+
+    /// The correctly rounded value, if it can be computed with exact operations.
+    ///
+    /// Clinger's fast path: when both the mantissa and the power of ten are exactly
+    /// representable, a single multiplication or division is correctly rounded.
+    pub fn to_f64(&self) -> Option<f64> {
+        if self.truncated {
+            return None;
+        }
+        let exponent = self.exponent();
+        let mut mantissa = self.mantissa;
+        let value = if mantissa == 0 {
+            0.
+        } else if mantissa > MAX_EXACT_INT {
+            return None;
+        } else if (0..=22).contains(&exponent) {
+            mantissa as f64 * EXACT_POWERS_OF_TEN[exponent as usize]
+        } else if (-22..0).contains(&exponent) {
+            mantissa as f64 / EXACT_POWERS_OF_TEN[-exponent as usize]
+        } else if (23..=22 + 15).contains(&exponent) {
+            // Move the extra zeros in the mantissa while it stays exact
+            for _ in 22..exponent {
+                mantissa *= 10;
+                if mantissa > MAX_EXACT_INT {
+                    return None;
+                }
+            }
+            mantissa as f64 * EXACT_POWERS_OF_TEN[22]
+        } else {
+            return None;
+        };
+        Some(if self.negative { -value } else { value })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn decimal_from_str(input: &str) -> Decimal {
+        let mut decimal = Decimal::default();
+        let mut in_fraction = false;
+        let mut in_exponent = false;
+        for &b in input.as_bytes() {
+            match b {
+                b'-' if in_exponent => decimal.set_exponent_negative(),
+                b'-' => decimal.set_negative(),
+                b'+' => (),
+                b'.' => in_fraction = true,
+                b'e' | b'E' => in_exponent = true,
+                _ if in_exponent => decimal.push_exponent_digit(b),
+                _ if in_fraction => decimal.push_fraction_digit(b),
+                _ => decimal.push_int_digit(b),
+            }
+        }
+        decimal
+    }
+
+    #[test]
+    fn lexeme_parts() {
+        let lexeme = NumberLexeme::new("-12.50e+3");
+        assert!(lexeme.negative);
+        assert_eq!(lexeme.integer, "12");
+        assert_eq!(lexeme.fraction, Some("50"));
+        assert_eq!(lexeme.exponent, Some("+3"));
+        let lexeme = NumberLexeme::new("0E5");
+        assert!(!lexeme.negative);
+        assert_eq!(lexeme.integer, "0");
+        assert_eq!(lexeme.fraction, None);
+        assert_eq!(lexeme.exponent, Some("5"));
+    }
+
+    #[test]
+    fn custom_numbers_are_json_numbers() {
+        for text in ["0", "-12.50e+3", "1E5", "-0.0"] {
+            assert_eq!(CustomNumber::new((), text).unwrap().text(), text);
+        }
+        let offset = |text| CustomNumber::new((), text).unwrap_err().offset;
+        assert_eq!(offset("oops"), 0);
+        assert_eq!(offset("-"), 1);
+        assert_eq!(offset("01"), 1);
+        assert_eq!(offset("1."), 2);
+        assert_eq!(offset("1e+"), 3);
+        assert_eq!(offset("+1"), 0);
+        assert_eq!(offset("1 "), 1);
+        assert_eq!(offset(""), 0);
+    }
+
+    fn assert_bit_identical(input: &str) {
+        let expected = f64::from_str(input).unwrap();
+        if let Some(value) = decimal_from_str(input).to_f64() {
+            assert_eq!(value.to_bits(), expected.to_bits(), "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn fast_path_values() {
+        assert_eq!(decimal_from_str("12.5e1").to_f64(), Some(125.));
+        assert_eq!(decimal_from_str("-0.25").to_f64(), Some(-0.25));
+        assert_eq!(decimal_from_str("1e30").to_f64(), Some(1e30));
+        assert_eq!(
+            decimal_from_str("-0").to_f64().unwrap().to_bits(),
+            (-0f64).to_bits()
+        );
+    }
+
+    #[test]
+    fn hard_cases_are_not_fast() {
+        assert!(decimal_from_str("9007199254740993").to_f64().is_none());
+        assert!(decimal_from_str("1e38").to_f64().is_none());
+        assert!(decimal_from_str("2.2250738585072014e-308")
+            .to_f64()
+            .is_none());
+        assert!(decimal_from_str("123456789012345678901234567890")
+            .to_f64()
+            .is_none());
+    }
+
+    #[test]
+    fn bit_identical_to_std() {
+        let samples = [
+            "0",
+            "-0",
+            "1",
+            "0.1",
+            "0.3",
+            "1.7976931348623157e308",
+            "5e-324",
+            "1e22",
+            "1e-22",
+            "123456789e-22",
+            "9007199254740992",
+            "4503599627370497.5",
+            "0.000001",
+            "3.14159",
+            "1e37",
+            "12e36",
+            "1e-400",
+            "1e400",
+            "0.0000000000000000000000000000001",
+            "100000000000000000000000000000000000000000000000000000001e-56",
+        ];
+        for sample in samples.iter() {
+            assert_bit_identical(sample);
+        }
+    }
+
+    #[test]
+    fn bit_identical_to_std_random() {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..100_000 {
+            let digits = next() % 10u64.pow((next() % 19) as u32 + 1);
+            let exponent = (next() % 80) as i64 - 40;
+            let input = format!("{}e{}", digits, exponent);
+            assert_bit_identical(&input);
+            let split = (next() % 10) as usize;
+            let digits = digits.to_string();
+            if split < digits.len() {
+                let input = format!("-{}.{}", &digits[..split.max(1)], &digits[split.max(1)..]);
+                if !input.ends_with('.') {
+                    assert_bit_identical(&input);
+                }
+            }
+        }
+    }
+}
@@ -20,158 +20,236 @@ OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 SOFTWARE.
 */
 
-use crate::lexer::{Lexer, Token, TokenInfo};
-use crate::JsonError;
+use crate::encoding::Transcoded;
+use crate::grammar::{Builder, Frame, Grammar};
+use crate::lexer::{Lexer, Token, TokenInfo, TokenKind};
+use crate::{
+    detect_encoding, BomPolicy, Context, CustomNumber, Dialect, Encoding, JsonError, JsonErrorKind,
+    Limit, ParseLimits, Wtf8String,
+};
 use std::collections::HashMap;
 
 // TODO Should I reimplement PartialEq to allow for float comparison?
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Json {
     Object(HashMap<String, Json>),
     Array(Vec<Json>),
     String(String),
+    /// A string with lone surrogates, see `SurrogatePolicy::Preserve`
+    Wtf8String(Wtf8String),
     Number(f64),
+    /// A number read by a number handler, see `Lexer::set_number_handler`
+    CustomNumber(CustomNumber),
     Boolean(bool),
     Null,
 }
 
+impl Json {
+    /// Drop the value without recursion, its children being moved to a stack
+    /// first. Dropping a value as usual recurses once per nesting level, which
+    /// can overflow the call stack for documents read with
+    /// `ParseLimits::unlimited()`.
+    pub fn drop_iteratively(self) {
+        let mut pending = vec![self];
+        while let Some(value) = pending.pop() {
+            match value {
+                Json::Array(vec) => pending.extend(vec),
+                Json::Object(map) => pending.extend(map.into_values()),
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Parse with the default limits, see `ParseLimits`.
 pub fn parse_json(input: &str) -> Result<Json, JsonError> {
+    parse_json_with_limits(input, ParseLimits::default())
+}
+
+pub fn parse_json_with_limits(input: &str, limits: ParseLimits) -> Result<Json, JsonError> {
     let lexer = Lexer::new(input);
+    let mut parser = Parser::with_limits(lexer, limits)?;
+    parser.parse()
+}
+
+/// Parse with the default limits, and the syntax of another dialect.
+pub fn parse_json_with_dialect(input: &str, dialect: Dialect) -> Result<Json, JsonError> {
+    let mut lexer = Lexer::new(input);
+    lexer.set_dialect(dialect);
     let mut parser = Parser::new(lexer)?;
     parser.parse()
 }
 
+/// Parse bytes with the default limits and BOM policy.
+///
+/// UTF-8 input is validated while being read. UTF-16 and UTF-32 input, see
+/// `detect_encoding`, is transcoded first, and the offsets of the errors are
+/// given in the original input.
+pub fn parse_slice(input: &[u8]) -> Result<Json, JsonError> {
+    parse_slice_with_options(input, BomPolicy::default(), ParseLimits::default())
+}
+
+pub fn parse_slice_with_options(
+    input: &[u8],
+    bom: BomPolicy,
+    limits: ParseLimits,
+) -> Result<Json, JsonError> {
+    if let Some(max) = limits.max_input_bytes {
+        if input.len() > max {
+            // Before transcoding, which would read it all
+            return Err(JsonError::new(
+                JsonErrorKind::LimitExceeded(Limit::InputBytes(max)),
+                Context::default(),
+            ));
+        }
+    }
+    let encoding = detect_encoding(input);
+    if encoding == Encoding::Utf8 {
+        let lexer = Lexer::from_slice(input, bom);
+        let mut parser = Parser::with_limits(lexer, limits)?;
+        return parser.parse();
+    }
+    let transcoded = Transcoded::new(input, encoding)?;
+    let lexer = Lexer::from_slice(transcoded.text.as_bytes(), bom);
+    Parser::with_limits(lexer, limits)
+        .and_then(|mut parser| parser.parse())
+        .map_err(|error| transcoded.map_error(error))
+}
+
+/// Builds `Json` values, an object keeping the key of the member being read.
+pub(crate) struct JsonBuilder;
+
+impl Builder for JsonBuilder {
+    type Value = Json;
+    type Array = Vec<Json>;
+    type Object = (HashMap<String, Json>, String);
+
+    fn scalar(&mut self, token_info: &TokenInfo) -> Json {
+        match &token_info.token {
+            Token::ValueNumber(n) => Json::Number(*n),
+            Token::ValueCustomNumber(n) => Json::CustomNumber(n.clone()),
+            Token::ValueBoolean(b) => Json::Boolean(*b),
+            Token::ValueString(s) => Json::String(s.to_string()),
+            Token::ValueWtf8String(s) => Json::Wtf8String(s.clone()),
+            _ => Json::Null,
+        }
+    }
+
+    fn start_array(&mut self, _open: &TokenInfo) -> Vec<Json> {
+        Vec::new()
+    }
+
+    fn push_element(&mut self, array: &mut Vec<Json>, value: Json) {
+        array.push(value);
+    }
+
+    fn end_array(&mut self, array: Vec<Json>, _close: &TokenInfo) -> Json {
+        Json::Array(array)
+    }
+
+    fn discard(&mut self, frame: Frame<Vec<Json>, Self::Object>) {
+        match frame {
+            Frame::Array(array) => Json::Array(array).drop_iteratively(),
+            Frame::Object((map, _)) => Json::Object(map).drop_iteratively(),
+        }
+    }
+
+    fn start_object(&mut self, _open: &TokenInfo) -> Self::Object {
+        (HashMap::new(), String::new())
+    }
+
+    fn key(&mut self, object: &mut Self::Object, key: String, _token_info: &TokenInfo) {
+        object.1 = key;
+    }
+
+    fn push_member(&mut self, object: &mut Self::Object, value: Json) {
+        let (map, key) = object;
+        map.insert(std::mem::take(key), value);
+    }
+
+    fn end_object(&mut self, object: Self::Object, _close: &TokenInfo) -> Json {
+        Json::Object(object.0)
+    }
+}
+
 pub struct Parser<'a> {
     pub lexer: Lexer<'a>,
     pub current_token_info: TokenInfo,
+    limits: ParseLimits,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(mut lexer: Lexer<'a>) -> Result<Self, JsonError> {
+    /// Create a parser with the default limits, see `ParseLimits`.
+    pub fn new(lexer: Lexer<'a>) -> Result<Self, JsonError> {
+        Self::with_limits(lexer, ParseLimits::default())
+    }
+
+    pub fn with_limits(mut lexer: Lexer<'a>, limits: ParseLimits) -> Result<Self, JsonError> {
+        lexer.set_limits(limits.clone());
         let token_info_result = lexer
             .next()
-            .ok_or_else(|| JsonError::Other(String::from("No data to parse")))?;
+            .ok_or_else(|| JsonError::new(JsonErrorKind::EmptyInput, lexer.context().clone()))?;
         let current_token_info = token_info_result?;
         Ok(Parser {
             lexer,
             current_token_info,
+            limits,
         })
     }
 
     pub fn parse(&mut self) -> Result<Json, JsonError> {
-        self.parse_json_value()
+        self.parse_with(JsonBuilder)
     }
 
-    fn build_parser_error(&self, message: String) -> JsonError {
-        JsonError::Parser {
-            message,
-            context: self.current_token_info.context.clone(),
+    /// Read the value starting at the current token with `builder`, and check
+    /// that only whitespace follows it.
+    pub(crate) fn parse_with<B: Builder>(&mut self, builder: B) -> Result<B::Value, JsonError> {
+        let mut grammar = Grammar::new(builder, self.limits.clone(), self.lexer.dialect());
+        grammar.token(&self.lexer, &self.current_token_info)?;
+        while !grammar.is_done() {
+            self.advance(grammar.expected())?;
+            grammar.token(&self.lexer, &self.current_token_info)?;
         }
+        self.expect_end()?;
+        Ok(grammar.into_root().expect("A complete grammar has a value"))
     }
 
-    fn advance(&mut self) -> Result<(), JsonError> {
-        let token_info_result = self
-            .lexer
-            .next()
-            .ok_or_else(|| JsonError::Other(String::from("No data to parse")))?;
+    /// Move to the next token, `expected` describing what is valid there.
+    fn advance(&mut self, expected: &[TokenKind]) -> Result<(), JsonError> {
+        let token_info_result = self.lexer.next().ok_or_else(|| {
+            JsonError::new(
+                JsonErrorKind::UnexpectedEof {
+                    expected: expected.to_vec(),
+                },
+                self.lexer.context().clone(),
+            )
+        })?;
         self.current_token_info = token_info_result?;
         Ok(())
     }
 
-    fn advance_and_validate(&mut self, token: Token) -> Result<(), JsonError> {
-        let token_result = self
-            .lexer
-            .next()
-            .ok_or_else(|| JsonError::Other(String::from("No data to parse")))??
-            .token;
-        if token_result == token {
-            Ok(())
-        } else {
-            Err(self.build_parser_error(format!(
-                "Was waiting {:?} but received {:?}",
-                token, token_result
-            )))
-        }
-    }
-
-    fn parse_json_value(&mut self) -> Result<Json, JsonError> {
-        let result = match &self.current_token_info.token {
-            Token::ArrayStart => Json::Array(self.parse_array()?),
-            Token::ObjectStart => Json::Object(self.parse_object()?),
-            Token::ValueNull => Json::Null,
-            Token::ValueNumber(n) => Json::Number(*n),
-            Token::ValueBoolean(b) => Json::Boolean(*b),
-            Token::ValueString(s) => Json::String(s.to_string()),
-            other => return Err(self.build_parser_error(format!("The token '{:?}' is not valid here, was waiting the start of an array, object or a value", other))),
-        };
-        Ok(result)
-    }
-
-    fn parse_array(&mut self) -> Result<Vec<Json>, JsonError> {
-        assert_eq!(self.current_token_info.token, Token::ArrayStart);
-        let mut vec = Vec::new();
-        self.advance()?;
-        if let Token::ArrayEnd = self.current_token_info.token {
-            return Ok(vec);
-        }
-        loop {
-            let value = self.parse_json_value()?;
-            vec.push(value);
-            self.advance()?;
-            match &self.current_token_info.token {
-                Token::ArrayEnd => return Ok(vec),
-                Token::SeparatorValue => {}
-                other => {
-                    return Err(self.build_parser_error(format!(
-                        "Was waiting a ',' or ']' but received {:?}",
-                        other
-                    )))
-                }
+    /// Check that only whitespace follows the document.
+    fn expect_end(&mut self) -> Result<(), JsonError> {
+        match self.lexer.next() {
+            None => Ok(()),
+            Some(result) => {
+                self.current_token_info = result?;
+                Err(JsonError::new(
+                    JsonErrorKind::TrailingToken(self.current_token_info.token.kind()),
+                    self.current_token_info.context.clone(),
+                ))
             }
-            self.advance()?;
-        }
-    }
-
-    fn parse_object(&mut self) -> Result<HashMap<String, Json>, JsonError> {
-        assert_eq!(self.current_token_info.token, Token::ObjectStart);
-        let mut map = HashMap::new();
-        self.advance()?;
-        if let Token::ObjectEnd = self.current_token_info.token {
-            return Ok(map);
-        }
-        loop {
-            let key = match &self.current_token_info.token {
-                Token::ValueString(val) => val.clone(),
-                other => {
-                    return Err(self.build_parser_error(format!(
-                        "Was waiting a string but received {:?}",
-                        other
-                    )))
-                }
-            };
-            self.advance_and_validate(Token::SeparatorName)?;
-            self.advance()?;
-            let value = self.parse_json_value()?;
-            map.insert(key, value);
-            self.advance()?;
-            match &self.current_token_info.token {
-                Token::ObjectEnd => return Ok(map),
-                Token::SeparatorValue => {}
-                other => {
-                    return Err(self.build_parser_error(format!(
-                        "Was waiting a ',' or '}}' but received {:?}",
-                        other
-                    )))
-                }
-            }
-            self.advance()?;
         }
     }
 }
 
 #[cfg(test)]
+// The oldest tests are kept as they were written
+#[allow(clippy::vec_init_then_push)]
 mod tests {
     use super::*;
+    use crate::grammar::ARRAY_FIRST;
+    use crate::NumberValue;
 
     fn cmp_input_and_result(input: &str, waited_result: Json) {
         let result = parse_json(input).unwrap();
@@ -257,4 +335,419 @@ mod tests {
         let target = Json::Array(vec);
         cmp_input_and_result(input, target);
     }
+
+    #[test]
+    fn mixed_nested_values() {
+        let input = r#" [{"a": [1, {}]}, [], [[null]], {"b": {"c": true}}] "#;
+        let mut map_c = HashMap::new();
+        map_c.insert("c".to_string(), Json::Boolean(true));
+        let mut map_b = HashMap::new();
+        map_b.insert("b".to_string(), Json::Object(map_c));
+        let mut map_a = HashMap::new();
+        map_a.insert(
+            "a".to_string(),
+            Json::Array(vec![Json::Number(1.), Json::Object(HashMap::new())]),
+        );
+        let target = Json::Array(vec![
+            Json::Object(map_a),
+            Json::Array(vec![]),
+            Json::Array(vec![Json::Array(vec![Json::Null])]),
+            Json::Object(map_b),
+        ]);
+        cmp_input_and_result(input, target);
+    }
+
+    #[test]
+    fn deeply_nested_arrays() {
+        let depth = 1_000_000;
+        let input = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        let mut json = parse_json_with_limits(&input, ParseLimits::unlimited()).unwrap();
+        for _ in 1..depth {
+            json = match json {
+                Json::Array(mut vec) if vec.len() == 1 => vec.pop().unwrap(),
+                _ => panic!("Wrong nesting"),
+            };
+        }
+        assert_eq!(json, Json::Array(vec![]));
+    }
+
+    #[test]
+    fn deeply_nested_objects() {
+        let depth = 1_000_000;
+        let input = format!("{}null{}", r#"{"a":"#.repeat(depth), "}".repeat(depth));
+        let json = parse_json_with_limits(&input, ParseLimits::unlimited()).unwrap();
+        json.drop_iteratively();
+    }
+
+    #[test]
+    fn deeply_nested_value_before_error() {
+        let depth = 1_000_000;
+        let input = format!("[{}{} x", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse_json_with_limits(&input, ParseLimits::unlimited()).is_err());
+    }
+
+    #[test]
+    fn move_out_of_values() {
+        let vec = match parse_json("[1]").unwrap() {
+            Json::Array(vec) => vec,
+            other => panic!("Not an array: {:?}", other),
+        };
+        assert_eq!(vec, vec![Json::Number(1.)]);
+        if let Json::Object(mut map) = parse_json(r#"{"a": "b"}"#).unwrap() {
+            match map.remove("a") {
+                Some(Json::String(s)) => assert_eq!(s, "b"),
+                other => panic!("Not a string: {:?}", other),
+            }
+        } else {
+            panic!("Not an object");
+        }
+    }
+
+    #[test]
+    fn deeply_nested_unclosed_arrays_is_error() {
+        let input = "[".repeat(1_000_000);
+        assert!(parse_json_with_limits(&input, ParseLimits::unlimited()).is_err());
+    }
+
+    fn parse_with_limits(input: &str, limits: ParseLimits) -> Result<Json, JsonError> {
+        let limits = ParseLimits {
+            max_depth: None,
+            ..limits
+        };
+        parse_json_with_limits(input, limits)
+    }
+
+    fn assert_limit_error(
+        result: Result<Json, JsonError>,
+        limit: Limit,
+        line: usize,
+        column: usize,
+    ) {
+        match result {
+            Err(JsonError {
+                kind: JsonErrorKind::LimitExceeded(candidate),
+                context,
+            }) => {
+                assert_eq!(candidate, limit);
+                assert_eq!((context.line, context.column), (line, column));
+            }
+            other => panic!("Was waiting a limit error, received {:?}", other),
+        }
+    }
+
+    #[test]
+    fn deeply_nested_arrays_with_default_limits_is_error() {
+        let depth = 1_000_000;
+        let input = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert_limit_error(parse_json(&input), Limit::Depth(128), 1, 129);
+    }
+
+    #[test]
+    fn depth_limit() {
+        let limits = ParseLimits {
+            max_depth: Some(2),
+            ..ParseLimits::unlimited()
+        };
+        assert!(parse_json_with_limits("[{}, [1]]", limits.clone()).is_ok());
+        let result = parse_json_with_limits(r#"[{"a": [1]}]"#, limits);
+        assert_limit_error(result, Limit::Depth(2), 1, 8);
+    }
+
+    #[test]
+    fn input_bytes_limit() {
+        let limits = ParseLimits {
+            max_input_bytes: Some(8),
+            ..ParseLimits::unlimited()
+        };
+        assert!(parse_with_limits("[1, 2]  ", limits.clone()).is_ok());
+        let result = parse_with_limits("[1, 2, 3]", limits.clone());
+        assert_limit_error(result, Limit::InputBytes(8), 1, 1);
+        // Trailing whitespace counts too
+        let result = parse_with_limits("1           ", limits.clone());
+        assert_limit_error(result, Limit::InputBytes(8), 1, 1);
+        let utf16: Vec<u8> = "[1, 2]".encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let result = parse_slice_with_options(&utf16, BomPolicy::Skip, limits);
+        assert_limit_error(result, Limit::InputBytes(8), 1, 1);
+    }
+
+    #[test]
+    fn string_length_limit() {
+        let limits = ParseLimits {
+            max_string_length: Some(3),
+            ..ParseLimits::unlimited()
+        };
+        assert!(parse_with_limits(r#"{"abc": "d\nf"}"#, limits.clone()).is_ok());
+        let result = parse_with_limits(r#"{"abc": "abcd"}"#, limits.clone());
+        assert_limit_error(result, Limit::StringLength(3), 1, 13);
+        let result = parse_with_limits(r#"{"abc": "ab\n\n"}"#, limits);
+        assert_limit_error(result, Limit::StringLength(3), 1, 16);
+    }
+
+    #[test]
+    fn container_elements_limit() {
+        let limits = ParseLimits {
+            max_container_elements: Some(2),
+            ..ParseLimits::unlimited()
+        };
+        assert!(parse_with_limits(r#"[[1, 2], {"a": 1, "b": 2}]"#, limits.clone()).is_ok());
+        let result = parse_with_limits(r#"{"a": 1, "b": 2, "c": 3}"#, limits.clone());
+        assert_limit_error(result, Limit::ContainerElements(2), 1, 23);
+        // Repeated keys are members too
+        let result = parse_with_limits(r#"{"a": 1, "a": 2, "a": 3}"#, limits);
+        assert_limit_error(result, Limit::ContainerElements(2), 1, 23);
+    }
+
+    #[test]
+    fn total_values_limit() {
+        let limits = ParseLimits {
+            max_total_values: Some(4),
+            ..ParseLimits::unlimited()
+        };
+        assert!(parse_with_limits("[[1], 2]", limits.clone()).is_ok());
+        let result = parse_with_limits("[[1], 2, 3]", limits);
+        assert_limit_error(result, Limit::TotalValues(4), 1, 11);
+    }
+
+    #[test]
+    fn number_digits_limit() {
+        let limits = ParseLimits {
+            max_number_digits: Some(4),
+            ..ParseLimits::unlimited()
+        };
+        assert!(parse_with_limits("-12.3e4", limits.clone()).is_ok());
+        let result = parse_with_limits("[-12.3e45]", limits);
+        assert_limit_error(result, Limit::NumberDigits(4), 1, 10);
+    }
+
+    #[test]
+    fn number_handler() {
+        let mut lexer = Lexer::new(r#"{"price": 19.90, "ratio": 0.5e1}"#);
+        lexer.set_number_handler(|lexeme| match lexeme.exponent {
+            Some(_) => Ok(NumberValue::Float(lexeme.text.parse().unwrap())),
+            None => Ok(NumberValue::Custom(CustomNumber::new((), lexeme.text)?)),
+        });
+        let json = Parser::new(lexer).unwrap().parse().unwrap();
+        let mut map = HashMap::new();
+        map.insert(
+            "price".to_string(),
+            Json::CustomNumber(CustomNumber::new((), "19.90").unwrap()),
+        );
+        map.insert("ratio".to_string(), Json::Number(5.));
+        assert_eq!(json, Json::Object(map));
+        assert_eq!(json.to_string(), r#"{"price":19.90,"ratio":5}"#);
+    }
+
+    fn parse_error(input: &str) -> JsonError {
+        parse_json(input).expect_err("Was waiting an error")
+    }
+
+    #[test]
+    fn trailing_tokens_are_errors() {
+        let error = parse_error("[1] 2");
+        assert_eq!(
+            error.kind,
+            JsonErrorKind::TrailingToken(TokenKind::ValueNumber)
+        );
+        assert_eq!(error.context.column, 5);
+        assert_eq!(
+            parse_error("{}}").kind,
+            JsonErrorKind::TrailingToken(TokenKind::ObjectEnd)
+        );
+        assert_eq!(
+            parse_error("1 @").kind,
+            JsonErrorKind::UnexpectedCharacter {
+                found: '@',
+                expected: None
+            }
+        );
+        assert_eq!(parse_json("\"a\" \n"), Ok(Json::String(String::from("a"))));
+    }
+
+    #[test]
+    fn empty_input_is_error_with_position() {
+        let error = parse_error(" \n ");
+        assert_eq!(error.kind, JsonErrorKind::EmptyInput);
+        assert_eq!(
+            error.context,
+            crate::Context {
+                line: 2,
+                column: 2,
+                offset: 3
+            }
+        );
+    }
+
+    #[test]
+    fn unexpected_eof_has_expected_tokens() {
+        let error = parse_error(r#"{"a": 1"#);
+        assert_eq!(
+            error.kind,
+            JsonErrorKind::UnexpectedEof {
+                expected: vec![TokenKind::SeparatorValue, TokenKind::ObjectEnd]
+            }
+        );
+        assert_eq!(
+            error.context,
+            crate::Context {
+                line: 1,
+                column: 8,
+                offset: 7
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Parser error, line 1 column 8: End of stream while waiting ',' or '}'"
+        );
+    }
+
+    #[test]
+    fn unexpected_token_has_found_and_expected_tokens() {
+        let error = parse_error(r#"{"a" 1}"#);
+        assert_eq!(
+            error.kind,
+            JsonErrorKind::UnexpectedToken {
+                found: TokenKind::ValueNumber,
+                expected: vec![TokenKind::SeparatorName]
+            }
+        );
+        assert_eq!(
+            error.context,
+            crate::Context {
+                line: 1,
+                column: 6,
+                offset: 5
+            }
+        );
+        let error = parse_error("[1, ]");
+        assert_eq!(
+            error.kind,
+            JsonErrorKind::UnexpectedToken {
+                found: TokenKind::ArrayEnd,
+                expected: TokenKind::VALUE_START.to_vec()
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "Parser error, line 1 column 5: Was waiting '{', '[', a string, a number, a boolean or null but received ']'"
+        );
+    }
+
+    #[test]
+    fn error_is_std_error() {
+        let error: Box<dyn std::error::Error> = Box::new(parse_error("[1 2]"));
+        assert_eq!(
+            error.to_string(),
+            "Parser error, line 1 column 4: Was waiting ',' or ']' but received a number"
+        );
+    }
+
+    #[test]
+    fn parse_slice_is_same_as_parse_json() {
+        let input = r#"{"a": ["é", 1.5, null], "🐱": true}"#;
+        assert_eq!(parse_slice(input.as_bytes()), parse_json(input));
+        let input = b"\xEF\xBB\xBF{\"a\": 1}";
+        assert_eq!(parse_slice(input), parse_json("{\"a\": 1}"));
+        assert!(
+            parse_slice_with_options(input, BomPolicy::Reject, ParseLimits::default()).is_err()
+        );
+    }
+
+    #[test]
+    fn parse_slice_reports_invalid_utf8() {
+        let error = parse_slice(b"{\"a\":\n  \"\xED\xA0\x80\"}").unwrap_err();
+        assert_eq!(error.kind, JsonErrorKind::InvalidUtf8(vec![0xED]));
+        assert_eq!((error.context.line, error.context.column), (2, 4));
+        assert_eq!(
+            error.to_string(),
+            "Lexer error, line 2 column 4: Invalid UTF-8 sequence [ED]"
+        );
+    }
+
+    #[test]
+    fn parse_slice_in_utf16_and_utf32() {
+        let text = "\u{FEFF}{\"a\": [\"é🐱\", 1.5, null]}";
+        let target = parse_json(&text[3..]);
+        let utf16le: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let utf16be: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        let utf32be: Vec<u8> = text
+            .chars()
+            .flat_map(|c| (c as u32).to_be_bytes())
+            .collect();
+        assert_eq!(parse_slice(&utf16le), target);
+        assert_eq!(parse_slice(&utf16be), target);
+        assert_eq!(parse_slice(&utf32be), target);
+        assert_eq!(parse_slice(&utf16le[2..]), target);
+        let error = parse_slice_with_options(&utf16le, BomPolicy::Reject, ParseLimits::default())
+            .unwrap_err();
+        assert_eq!((error.context.line, error.context.offset), (1, 0));
+    }
+
+    #[test]
+    fn parse_slice_errors_have_original_offsets() {
+        let utf16le: Vec<u8> = "[\"🐱\",\n 1 2]"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        let error = parse_slice(&utf16le).unwrap_err();
+        assert_eq!(
+            error.context,
+            crate::Context {
+                line: 2,
+                column: 4,
+                offset: 20
+            }
+        );
+    }
+
+    #[test]
+    fn jsonc_comments_and_trailing_commas() {
+        let input =
+            "{\n  // The port\n  \"port\": 80, /* default */\n  \"tags\": [\"a\", \"b\",],\n}";
+        let mut map = HashMap::new();
+        map.insert("port".to_string(), Json::Number(80.));
+        map.insert(
+            "tags".to_string(),
+            Json::Array(vec![Json::String("a".into()), Json::String("b".into())]),
+        );
+        assert_eq!(
+            parse_json_with_dialect(input, Dialect::Jsonc),
+            Ok(Json::Object(map))
+        );
+        assert!(parse_json(input).is_err());
+        assert!(parse_json_with_dialect("[1,,]", Dialect::Jsonc).is_err());
+        assert!(parse_json_with_dialect("[,]", Dialect::Jsonc).is_err());
+        assert!(parse_json_with_dialect("{,}", Dialect::Jsonc).is_err());
+    }
+
+    #[test]
+    fn trailing_commas_are_errors_in_json() {
+        let error = parse_error("[1,]");
+        assert_eq!(
+            error.kind,
+            JsonErrorKind::UnexpectedToken {
+                found: TokenKind::ArrayEnd,
+                expected: TokenKind::VALUE_START.to_vec()
+            }
+        );
+        let error = parse_json_with_dialect("[1,:]", Dialect::Jsonc).unwrap_err();
+        assert_eq!(
+            error.kind,
+            JsonErrorKind::UnexpectedToken {
+                found: TokenKind::SeparatorName,
+                expected: ARRAY_FIRST.to_vec()
+            }
+        );
+    }
+
+    #[test]
+    fn lone_surrogates_in_keys_are_errors() {
+        let mut lexer = Lexer::new(r#"{"a\ud800":1,"a\udbff":2}"#);
+        lexer.set_surrogate_policy(crate::SurrogatePolicy::Preserve);
+        let error = Parser::new(lexer).unwrap().parse().unwrap_err();
+        assert_eq!(
+            error.kind,
+            JsonErrorKind::InvalidUnicodeEscape(String::from("d800"))
+        );
+        assert_eq!((error.context.line, error.context.column), (1, 2));
+    }
 }
@@ -0,0 +1,184 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Conversions between byte offsets and line and column positions.
+
+use crate::Context;
+
+/// Unit counted by columns. Lines and columns both start at 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColumnEncoding {
+    /// Bytes of the UTF-8 input
+    Utf8Bytes,
+    /// Unicode scalar values, the default
+    #[default]
+    Chars,
+    /// UTF-16 code units, as used by the Language Server Protocol
+    Utf16,
+}
+
+impl ColumnEncoding {
+    /// Columns taken by a single byte of UTF-8, counted on the lead byte of
+    /// each character.
+    pub(crate) fn byte_width(self, b: u8) -> usize {
+        let continuation = b & 0xC0 == 0x80;
+        match self {
+            ColumnEncoding::Utf8Bytes => 1,
+            ColumnEncoding::Chars => !continuation as usize,
+            // Characters of 4 bytes are encoded as surrogate pairs
+            ColumnEncoding::Utf16 if b >= 0xF0 => 2,
+            ColumnEncoding::Utf16 => !continuation as usize,
+        }
+    }
+
+    pub(crate) fn width(self, bytes: &[u8]) -> usize {
+        match self {
+            ColumnEncoding::Utf8Bytes => bytes.len(),
+            _ => bytes.iter().map(|&b| self.byte_width(b)).sum(),
+        }
+    }
+}
+
+/// Start offsets of the lines of a source, to convert positions both ways.
+///
+/// Lines end with '\n', a preceding '\r' is counted as part of the line.
+pub struct LineIndex<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        LineIndex {
+            source,
+            line_starts,
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Position of a byte offset, `None` if it is out of the source or not on
+    /// a character boundary.
+    pub fn context(&self, offset: usize, encoding: ColumnEncoding) -> Option<Context> {
+        if !self.source.is_char_boundary(offset) {
+            return None;
+        }
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+        let line_start = self.line_starts[line];
+        let column = encoding.width(&self.source.as_bytes()[line_start..offset]) + 1;
+        Some(Context {
+            line: line + 1,
+            column,
+            offset,
+        })
+    }
+
+    /// Byte offset of a position, `None` if the line does not exist or if the
+    /// column is past the end of the line or inside a character.
+    pub fn offset(&self, line: usize, column: usize, encoding: ColumnEncoding) -> Option<usize> {
+        let line_start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let line_end = self
+            .line_starts
+            .get(line)
+            .map(|next| next - 1)
+            .unwrap_or(self.source.len());
+        let mut remaining = column.checked_sub(1)?;
+        for (idx, c) in self.source[line_start..line_end].char_indices() {
+            if remaining == 0 {
+                return Some(line_start + idx);
+            }
+            let mut buffer = [0; 4];
+            remaining =
+                remaining.checked_sub(encoding.width(c.encode_utf8(&mut buffer).as_bytes()))?;
+        }
+        match remaining {
+            0 => Some(line_end),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "{\n\t\"é🐱\": 1\r\n}";
+
+    #[test]
+    fn widths() {
+        let cat = "🐱".as_bytes();
+        assert_eq!(ColumnEncoding::Utf8Bytes.width(cat), 4);
+        assert_eq!(ColumnEncoding::Chars.width(cat), 1);
+        assert_eq!(ColumnEncoding::Utf16.width(cat), 2);
+        assert_eq!(ColumnEncoding::Utf16.width("é".as_bytes()), 1);
+    }
+
+    #[test]
+    fn offset_to_context() {
+        let index = LineIndex::new(SOURCE);
+        assert_eq!(index.line_count(), 3);
+        let one = SOURCE.find('1').unwrap();
+        let columns = |encoding| index.context(one, encoding).map(|c| (c.line, c.column));
+        assert_eq!(columns(ColumnEncoding::Utf8Bytes), Some((2, 12)));
+        assert_eq!(columns(ColumnEncoding::Chars), Some((2, 8)));
+        assert_eq!(columns(ColumnEncoding::Utf16), Some((2, 9)));
+        let context = index.context(0, ColumnEncoding::Chars).unwrap();
+        assert_eq!(context, Context::default());
+        let end = index.context(SOURCE.len(), ColumnEncoding::Chars).unwrap();
+        assert_eq!((end.line, end.column), (3, 2));
+        assert_eq!(index.context(5, ColumnEncoding::Chars), None);
+        assert_eq!(index.context(SOURCE.len() + 1, ColumnEncoding::Chars), None);
+    }
+
+    #[test]
+    fn context_to_offset() {
+        let index = LineIndex::new(SOURCE);
+        for encoding in [
+            ColumnEncoding::Utf8Bytes,
+            ColumnEncoding::Chars,
+            ColumnEncoding::Utf16,
+        ]
+        .iter()
+        {
+            for (offset, _) in SOURCE.char_indices().chain(Some((SOURCE.len(), ' '))) {
+                let context = index.context(offset, *encoding).unwrap();
+                assert_eq!(
+                    index.offset(context.line, context.column, *encoding),
+                    Some(offset)
+                );
+            }
+        }
+        // Inside the surrogate pair of the cat
+        assert_eq!(index.offset(2, 5, ColumnEncoding::Utf16), None);
+        assert_eq!(index.offset(1, 3, ColumnEncoding::Chars), None);
+        assert_eq!(index.offset(4, 1, ColumnEncoding::Chars), None);
+        assert_eq!(index.offset(1, 0, ColumnEncoding::Chars), None);
+    }
+}
@@ -0,0 +1,748 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Parsing mode which does not stop at the first error, for editors which want
+//! to report every syntax error of a document at once.
+
+use crate::grammar::{unexpected, Frame, Grammar, State};
+use crate::grammar::{ARRAY_FIRST, ARRAY_NEXT, OBJECT_KEY, OBJECT_NEXT, OBJECT_SEPARATOR};
+use crate::lexer::{Lexer, Token, TokenInfo, TokenKind};
+use crate::parser::JsonBuilder;
+use crate::repair::{close_string, is_json_token, Repair, RepairKind};
+use crate::{Context, Dialect, Json, JsonError, JsonErrorKind, ParseLimits};
+
+/// Result of a recovering parse.
+///
+/// Missing values are replaced with `Json::Null` placeholders, and unclosed
+/// arrays and objects are closed, so that `value` is always a complete tree.
+#[derive(Debug)]
+pub struct Recovered {
+    pub value: Json,
+    pub errors: Vec<JsonError>,
+}
+
+/// Parse the whole input, resynchronizing after errors at `,`, `]` and `}`.
+///
+/// Parsing stops after `max_errors` errors, `0` meaning no maximum, or at the
+/// first exceeded limit.
+pub fn parse_json_recovering(input: &str, max_errors: usize) -> Recovered {
+    parse_json_recovering_with_limits(input, max_errors, ParseLimits::default())
+}
+
+pub fn parse_json_recovering_with_limits(
+    input: &str,
+    max_errors: usize,
+    limits: ParseLimits,
+) -> Recovered {
+    let max_errors = if max_errors == 0 {
+        usize::MAX
+    } else {
+        max_errors
+    };
+    let mut recovery = Recovery::new(Lexer::new(input), limits, max_errors);
+    recovery.run();
+    recovery.finish()
+}
+
+/// The recovering parser, which also records the fixes matching the way it
+/// resynchronizes, for `repair_json`.
+///
+/// Valid tokens go through the grammar, the others are fixed up by changing
+/// its state.
+pub(crate) struct Recovery<'a> {
+    pub(crate) lexer: Lexer<'a>,
+    grammar: Grammar<JsonBuilder>,
+    max_errors: usize,
+    errors: Vec<JsonError>,
+    pub(crate) repairs: Vec<Repair>,
+    /// Close the strings cut by the end of the input instead of dropping them
+    pub(crate) close_strings: bool,
+    /// Record the JSON5 syntax as repairs, and replace the numbers which are
+    /// not finite with `null`
+    pub(crate) convert_json5: bool,
+    /// Position of the last ',' read, to report a trailing comma
+    comma: Context,
+    /// Ignoring tokens up to the next `,` or closing token of the current
+    /// container, counting the containers opened in between
+    skip: Option<usize>,
+    /// Whether a token was found after the end of the value
+    trailing: bool,
+}
+
+impl<'a> Recovery<'a> {
+    pub(crate) fn new(mut lexer: Lexer<'a>, limits: ParseLimits, max_errors: usize) -> Self {
+        lexer.set_limits(limits.clone());
+        Recovery {
+            lexer,
+            // Trailing commas are reported whatever the dialect
+            grammar: Grammar::new(JsonBuilder, limits, Dialect::Json),
+            max_errors,
+            errors: Vec::new(),
+            repairs: Vec::new(),
+            close_strings: false,
+            convert_json5: false,
+            comma: Context::default(),
+            skip: None,
+            trailing: false,
+        }
+    }
+
+    /// Whether the top level value was read completely.
+    fn is_done(&self) -> bool {
+        self.grammar.is_done()
+    }
+
+    pub(crate) fn run(&mut self) {
+        while self.errors.len() < self.max_errors {
+            let is_done = self.is_done();
+            let result = match self.next_token() {
+                None => {
+                    if let Err(error) = self.handle_eof() {
+                        self.push_error(error);
+                    }
+                    break;
+                }
+                Some(result) if is_done => {
+                    self.handle_trailing(result);
+                    Ok(())
+                }
+                Some(Err(error)) => self.handle_lexer_error(error),
+                Some(Ok(token_info)) => self.handle_token(token_info),
+            };
+            if let Err(error) = result {
+                self.push_error(error);
+                break;
+            }
+            if let Some(JsonErrorKind::LimitExceeded(_)) = self.errors.last().map(|e| &e.kind) {
+                break;
+            }
+        }
+        let context = self.lexer.context().clone();
+        while let Some(frame) = self.grammar.stack.last() {
+            let closing = match frame {
+                Frame::Array(_) => Token::ArrayEnd,
+                Frame::Object(_) => Token::ObjectEnd,
+            };
+            self.repair(RepairKind::InsertedClosing(closing.kind()), &context);
+            // An exceeded limit was already reported, the value is kept
+            self.grammar.close(&inserted(closing, &context)).ok();
+        }
+    }
+
+    pub(crate) fn finish(self) -> Recovered {
+        Recovered {
+            value: self.grammar.into_root().unwrap_or(Json::Null),
+            errors: self.errors,
+        }
+    }
+
+    /// The next token of the lexer, the whitespace and comments it may emit
+    /// being skipped.
+    fn next_token(&mut self) -> Option<Result<TokenInfo, JsonError>> {
+        loop {
+            let token_info = match self.lexer.next()? {
+                Ok(token_info) => token_info,
+                error => return Some(error),
+            };
+            match token_info.token {
+                // Removed with the trailing text
+                Token::Comment | Token::Whitespace if self.trailing => (),
+                Token::Comment => self.repair(RepairKind::RemovedComment, &token_info.context),
+                Token::Whitespace => {
+                    let raw = self.lexer.raw_text(&token_info).unwrap_or_default();
+                    if raw
+                        .bytes()
+                        .any(|b| !matches!(b, b' ' | b'\t' | b'\r' | b'\n'))
+                    {
+                        let kind = RepairKind::ConvertedToken(TokenKind::Whitespace);
+                        self.repair(kind, &token_info.context);
+                    }
+                }
+                _ => return Some(Ok(token_info)),
+            }
+        }
+    }
+
+    /// Report a token or an error after the end of the value, the text being
+    /// removed from the first one.
+    fn handle_trailing(&mut self, result: Result<TokenInfo, JsonError>) {
+        let error = match result {
+            Ok(token_info) => JsonError::new(
+                JsonErrorKind::TrailingToken(token_info.token.kind()),
+                token_info.context,
+            ),
+            Err(error) => error,
+        };
+        if !self.trailing {
+            self.trailing = true;
+            self.repair(RepairKind::RemovedTrailingText, &error.context);
+        }
+        self.push_error(error);
+    }
+
+    fn handle_lexer_error(&mut self, error: JsonError) -> Result<(), JsonError> {
+        let context = error.context.clone();
+        let kind = error.kind.clone();
+        self.push_error(error);
+        let start = self.lexer.token_start().offset;
+        let raw = self.lexer.raw(start, context.offset);
+        // The end of the input inside a string, maybe in an escape
+        let is_cut_string = match &kind {
+            JsonErrorKind::UnterminatedString => true,
+            JsonErrorKind::UnexpectedEof { expected } => {
+                expected.is_empty() && matches!(raw.first(), Some(b'"' | b'\''))
+            }
+            _ => false,
+        };
+        if is_cut_string && self.close_strings && self.skip.is_none() {
+            let text = close_string(raw);
+            match self.grammar.state {
+                State::Value(_) => {
+                    self.repair(RepairKind::ClosedString, &context);
+                    return self.grammar.complete(Json::String(text), &context);
+                }
+                State::Key(_) => {
+                    self.repair(RepairKind::ClosedString, &context);
+                    let token = Token::ValueString(text.clone());
+                    self.grammar.set_key(text, &inserted(token, &context));
+                    return Ok(());
+                }
+                _ => (),
+            }
+        }
+        if let JsonErrorKind::LimitExceeded(_) = kind {
+            return Ok(());
+        }
+        self.repair(RepairKind::RemovedInvalidText(kind), &context);
+        match (self.skip, self.grammar.state) {
+            (None, State::Value(_)) => self.grammar.complete(Json::Null, &context),
+            _ => Ok(()),
+        }
+    }
+
+    /// Record an error, unless another one was already reported at this place.
+    fn push_error(&mut self, error: JsonError) {
+        if self.errors.len() >= self.max_errors {
+            return;
+        }
+        if let Some(last) = self.errors.last() {
+            if last.context == error.context {
+                return;
+            }
+        }
+        self.errors.push(error);
+    }
+
+    fn repair(&mut self, kind: RepairKind, context: &Context) {
+        self.repairs.push(Repair {
+            kind,
+            context: context.clone(),
+        });
+    }
+
+    fn unexpected(&mut self, token_info: &TokenInfo, expected: &[TokenKind]) {
+        self.push_error(unexpected(token_info, expected));
+    }
+
+    fn is_in_array(&self) -> bool {
+        matches!(self.grammar.stack.last(), Some(Frame::Array(_)))
+    }
+
+    /// Wait the token after an element of the current container.
+    fn resume(&mut self) {
+        self.skip = None;
+        self.grammar.state = match self.grammar.stack.last() {
+            Some(Frame::Array(_)) => State::ArrayNext,
+            Some(Frame::Object(_)) => State::ObjectNext,
+            None => State::Done,
+        };
+    }
+
+    /// Handle a token, only failing on exceeded limits.
+    fn handle_token(&mut self, token_info: TokenInfo) -> Result<(), JsonError> {
+        let kind = token_info.token.kind();
+        let context = &token_info.context;
+        // A token may be handled again after a state change, to resynchronize
+        loop {
+            if let Some(nesting) = self.skip {
+                match kind {
+                    TokenKind::ArrayEnd | TokenKind::ObjectEnd | TokenKind::SeparatorValue
+                        if nesting == 0 =>
+                    {
+                        self.resume();
+                        continue;
+                    }
+                    _ => {
+                        self.repair(RepairKind::RemovedToken(kind), context);
+                        self.skip = Some(match kind {
+                            TokenKind::ArrayStart | TokenKind::ObjectStart => nesting + 1,
+                            TokenKind::ArrayEnd | TokenKind::ObjectEnd => nesting - 1,
+                            _ => nesting,
+                        });
+                        return Ok(());
+                    }
+                }
+            }
+            let state = self.grammar.state;
+            if let (true, State::Value(_), Token::ValueNumber(n)) =
+                (self.convert_json5, state, &token_info.token)
+            {
+                if !n.is_finite() {
+                    let raw = self
+                        .lexer
+                        .raw_text(&token_info)
+                        .unwrap_or_default()
+                        .to_string();
+                    self.repair(RepairKind::ReplacedLiteral(raw), context);
+                    return self.grammar.complete(Json::Null, context);
+                }
+            }
+            match self.grammar.token(&self.lexer, &token_info) {
+                Ok(()) => {
+                    if self.convert_json5
+                        && matches!(kind, TokenKind::ValueString | TokenKind::ValueNumber)
+                        && !is_json_token(self.lexer.raw_text(&token_info).unwrap_or_default())
+                    {
+                        self.repair(RepairKind::ConvertedToken(kind), context);
+                    }
+                    match (state, kind) {
+                        (State::Key(_), TokenKind::ValueString) => (),
+                        (State::Key(_), TokenKind::ObjectEnd) => (),
+                        (State::Key(_), _) => self.repair(RepairKind::QuotedKey, context),
+                        (_, TokenKind::SeparatorValue) => self.comma = context.clone(),
+                        _ => (),
+                    }
+                    return Ok(());
+                }
+                Err(error) => match error.kind {
+                    JsonErrorKind::LimitExceeded(_) => return Err(error),
+                    JsonErrorKind::UnexpectedToken { .. } => (),
+                    // A key which cannot be one, skipped as any other token
+                    _ => self.push_error(error),
+                },
+            }
+            match state {
+                State::Value(expected) => match &token_info.token {
+                    Token::ArrayEnd | Token::ObjectEnd | Token::SeparatorValue => {
+                        self.unexpected(&token_info, expected);
+                        if self.grammar.stack.is_empty() {
+                            self.repair(RepairKind::RemovedToken(kind), context);
+                            return Ok(());
+                        }
+                        let is_array_end = kind == TokenKind::ArrayEnd;
+                        if self.is_in_array() && kind != TokenKind::SeparatorValue {
+                            // Trailing comma, or wrong closing token
+                            if expected != ARRAY_FIRST {
+                                let comma = self.comma.clone();
+                                self.repair(RepairKind::RemovedTrailingComma, &comma);
+                            }
+                            if !is_array_end {
+                                self.repair(
+                                    RepairKind::InsertedClosing(TokenKind::ArrayEnd),
+                                    context,
+                                );
+                            }
+                            self.grammar.close(&token_info)?;
+                            if is_array_end {
+                                return Ok(());
+                            }
+                        } else {
+                            self.repair(RepairKind::InsertedNull, context);
+                            self.grammar.complete(Json::Null, context)?;
+                        }
+                        continue;
+                    }
+                    Token::Identifier(name) => match python_literal(name) {
+                        Some(value) => {
+                            self.repair(RepairKind::ReplacedLiteral(name.clone()), context);
+                            self.grammar.complete(value, context)?;
+                        }
+                        None => {
+                            self.unexpected(&token_info, expected);
+                            self.repair(RepairKind::RemovedToken(kind), context);
+                        }
+                    },
+                    _ => {
+                        self.unexpected(&token_info, expected);
+                        self.repair(RepairKind::RemovedToken(kind), context);
+                    }
+                },
+                State::ArrayNext => match kind {
+                    TokenKind::ObjectEnd => {
+                        self.unexpected(&token_info, ARRAY_NEXT);
+                        self.repair(RepairKind::InsertedClosing(TokenKind::ArrayEnd), context);
+                        self.grammar.close(&token_info)?;
+                        continue;
+                    }
+                    TokenKind::SeparatorName => {
+                        self.unexpected(&token_info, ARRAY_NEXT);
+                        self.repair(RepairKind::RemovedToken(kind), context);
+                    }
+                    _ => {
+                        // Missing comma
+                        self.unexpected(&token_info, ARRAY_NEXT);
+                        self.repair(RepairKind::InsertedComma, context);
+                        self.grammar.state = State::Value(TokenKind::VALUE_START);
+                        continue;
+                    }
+                },
+                State::Key(expected) => match kind {
+                    TokenKind::ObjectEnd => {
+                        // Only after a comma, the grammar closes empty objects
+                        self.unexpected(&token_info, expected);
+                        let comma = self.comma.clone();
+                        self.repair(RepairKind::RemovedTrailingComma, &comma);
+                        self.grammar.close(&token_info)?;
+                    }
+                    TokenKind::ArrayEnd => {
+                        self.unexpected(&token_info, expected);
+                        self.repair(RepairKind::InsertedClosing(TokenKind::ObjectEnd), context);
+                        self.grammar.close(&token_info)?;
+                        continue;
+                    }
+                    TokenKind::SeparatorValue => {
+                        self.unexpected(&token_info, expected);
+                        self.repair(RepairKind::RemovedToken(kind), context);
+                    }
+                    _ => {
+                        self.unexpected(&token_info, expected);
+                        self.skip = Some(0);
+                        continue;
+                    }
+                },
+                State::Colon => match kind {
+                    TokenKind::SeparatorValue | TokenKind::ObjectEnd | TokenKind::ArrayEnd => {
+                        self.unexpected(&token_info, OBJECT_SEPARATOR);
+                        self.repair(RepairKind::InsertedNull, context);
+                        self.grammar.complete(Json::Null, context)?;
+                        continue;
+                    }
+                    _ => {
+                        // Missing ':'
+                        self.unexpected(&token_info, OBJECT_SEPARATOR);
+                        self.repair(RepairKind::InsertedColon, context);
+                        self.grammar.state = State::Value(TokenKind::VALUE_START);
+                        continue;
+                    }
+                },
+                State::ObjectNext => match kind {
+                    TokenKind::ArrayEnd => {
+                        self.unexpected(&token_info, OBJECT_NEXT);
+                        self.repair(RepairKind::InsertedClosing(TokenKind::ObjectEnd), context);
+                        self.grammar.close(&token_info)?;
+                        continue;
+                    }
+                    TokenKind::ValueString | TokenKind::Identifier => {
+                        // Missing comma
+                        self.unexpected(&token_info, OBJECT_NEXT);
+                        self.repair(RepairKind::InsertedComma, context);
+                        self.grammar.state = State::Key(OBJECT_KEY);
+                        continue;
+                    }
+                    _ => {
+                        self.unexpected(&token_info, OBJECT_NEXT);
+                        self.skip = Some(0);
+                        continue;
+                    }
+                },
+                State::Done => (),
+            }
+            return Ok(());
+        }
+    }
+
+    fn handle_eof(&mut self) -> Result<(), JsonError> {
+        let context = self.lexer.context().clone();
+        if self.grammar.is_empty() && self.grammar.root().is_none() {
+            self.push_error(JsonError::new(JsonErrorKind::EmptyInput, context.clone()));
+            self.repair(RepairKind::InsertedNull, &context);
+            return Ok(());
+        }
+        let expected = match (self.skip, self.grammar.state) {
+            (_, State::Done) => return Ok(()),
+            (Some(_), _) if self.is_in_array() => ARRAY_NEXT,
+            (Some(_), _) => OBJECT_NEXT,
+            _ => self.grammar.expected(),
+        };
+        self.push_error(JsonError::new(
+            JsonErrorKind::UnexpectedEof {
+                expected: expected.to_vec(),
+            },
+            context.clone(),
+        ));
+        if (expected == TokenKind::VALUE_START && self.is_in_array()) || expected == OBJECT_KEY {
+            let comma = self.comma.clone();
+            self.repair(RepairKind::RemovedTrailingComma, &comma);
+        }
+        if self.skip.is_none() {
+            if let State::Value(_) | State::Colon = self.grammar.state {
+                if !self.is_in_array() {
+                    self.repair(RepairKind::InsertedNull, &context);
+                    return self.grammar.complete(Json::Null, &context);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A token which was not in the input, to fix it.
+fn inserted(token: Token, context: &Context) -> TokenInfo {
+    TokenInfo {
+        token,
+        context: context.clone(),
+        end: context.clone(),
+    }
+}
+
+/// The JSON value of a Python literal, as written by `repr` on a `dict`.
+fn python_literal(name: &str) -> Option<Json> {
+    match name {
+        "True" => Some(Json::Boolean(true)),
+        "False" => Some(Json::Boolean(false)),
+        "None" => Some(Json::Null),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_json, Context, Limit};
+
+    fn object(members: Vec<(&str, Json)>) -> Json {
+        let map = members
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect();
+        Json::Object(map)
+    }
+
+    fn positions(errors: &[JsonError]) -> Vec<(usize, usize)> {
+        errors
+            .iter()
+            .map(|error| (error.context.line, error.context.column))
+            .collect()
+    }
+
+    #[test]
+    fn valid_document_has_no_error() {
+        let input = r#" {"one": [1, {"two": null}], "three": "3"} "#;
+        let recovered = parse_json_recovering(input, 10);
+        assert!(recovered.errors.is_empty());
+        assert_eq!(recovered.value, parse_json(input).unwrap());
+    }
+
+    #[test]
+    fn missing_values_get_placeholders() {
+        let recovered = parse_json_recovering(r#"[1, , 3, {"a": , "b": }]"#, 10);
+        assert_eq!(positions(&recovered.errors), vec![(1, 5), (1, 16), (1, 23)]);
+        let target = Json::Array(vec![
+            Json::Number(1.),
+            Json::Null,
+            Json::Number(3.),
+            object(vec![("a", Json::Null), ("b", Json::Null)]),
+        ]);
+        assert_eq!(recovered.value, target);
+    }
+
+    #[test]
+    fn missing_commas_and_colons() {
+        let recovered = parse_json_recovering(r#"{"a": 1 "b" 2, "c": [true false]}"#, 10);
+        assert_eq!(positions(&recovered.errors), vec![(1, 9), (1, 13), (1, 27)]);
+        let target = object(vec![
+            ("a", Json::Number(1.)),
+            ("b", Json::Number(2.)),
+            (
+                "c",
+                Json::Array(vec![Json::Boolean(true), Json::Boolean(false)]),
+            ),
+        ]);
+        assert_eq!(recovered.value, target);
+    }
+
+    #[test]
+    fn trailing_commas() {
+        let recovered = parse_json_recovering(r#"[{"a": 1,}, 2,]"#, 10);
+        assert_eq!(positions(&recovered.errors), vec![(1, 10), (1, 15)]);
+        let target = Json::Array(vec![
+            object(vec![("a", Json::Number(1.))]),
+            Json::Number(2.),
+        ]);
+        assert_eq!(recovered.value, target);
+    }
+
+    #[test]
+    fn lexer_errors_and_bad_keys() {
+        let recovered = parse_json_recovering(r#"[tru, 'a', {1: [2], "b": x}, 3]"#, 10);
+        let target = Json::Array(vec![
+            Json::Null,
+            Json::Null,
+            object(vec![("b", Json::Null)]),
+            Json::Number(3.),
+        ]);
+        assert_eq!(recovered.value, target);
+        // One error for the literal, three for the quotes and their content
+        assert_eq!(recovered.errors.len(), 6);
+    }
+
+    #[test]
+    fn unclosed_containers_are_closed() {
+        let recovered = parse_json_recovering("[1, {\"a\": [2", 10);
+        assert_eq!(recovered.errors.len(), 1);
+        assert_eq!(
+            recovered.errors[0].kind,
+            JsonErrorKind::UnexpectedEof {
+                expected: ARRAY_NEXT.to_vec()
+            }
+        );
+        let target = Json::Array(vec![
+            Json::Number(1.),
+            object(vec![("a", Json::Array(vec![Json::Number(2.)]))]),
+        ]);
+        assert_eq!(recovered.value, target);
+    }
+
+    #[test]
+    fn mismatched_closing_tokens() {
+        let recovered = parse_json_recovering(r#"[1, {"a": 2], 3}"#, 10);
+        // The array is closed, the tokens after it are trailing
+        assert_eq!(
+            positions(&recovered.errors),
+            vec![(1, 12), (1, 13), (1, 15), (1, 16)]
+        );
+        let target = Json::Array(vec![
+            Json::Number(1.),
+            object(vec![("a", Json::Number(2.))]),
+        ]);
+        assert_eq!(recovered.value, target);
+    }
+
+    #[test]
+    fn errors_are_capped() {
+        let recovered = parse_json_recovering("[,,,,,,]", 2);
+        assert_eq!(recovered.errors.len(), 2);
+        assert_eq!(recovered.value, Json::Array(vec![Json::Null, Json::Null]));
+    }
+
+    #[test]
+    fn zero_means_no_maximum() {
+        let recovered = parse_json_recovering("[1, 2]", 0);
+        assert!(recovered.errors.is_empty());
+        assert_eq!(
+            recovered.value,
+            Json::Array(vec![Json::Number(1.), Json::Number(2.)])
+        );
+        let recovered = parse_json_recovering("[,,,,,,]", 0);
+        assert_eq!(recovered.errors.len(), 7);
+    }
+
+    #[test]
+    fn custom_limits() {
+        let limits = ParseLimits {
+            max_depth: Some(2),
+            ..ParseLimits::unlimited()
+        };
+        let recovered = parse_json_recovering_with_limits("[[[1]]]", 10, limits);
+        assert_eq!(
+            recovered.errors[0].kind,
+            JsonErrorKind::LimitExceeded(Limit::Depth(2))
+        );
+        let input = format!("{}{}", "[".repeat(1000), "]".repeat(1000));
+        let recovered = parse_json_recovering_with_limits(&input, 10, ParseLimits::unlimited());
+        assert!(recovered.errors.is_empty());
+    }
+
+    #[test]
+    fn empty_input() {
+        let recovered = parse_json_recovering("  ", 10);
+        assert_eq!(recovered.value, Json::Null);
+        assert_eq!(recovered.errors[0].kind, JsonErrorKind::EmptyInput);
+        assert_eq!(
+            recovered.errors[0].context,
+            Context {
+                line: 1,
+                column: 3,
+                offset: 2
+            }
+        );
+    }
+
+    #[test]
+    fn first_error_is_the_strict_parser_error() {
+        let inputs = [
+            "[1 2]",
+            "[1,]",
+            r#"{"a" 1}"#,
+            r#"{"a": 1,}"#,
+            r#"{1: 2}"#,
+            "[1, {\"a\": [2",
+            "[\"a\\x\"]",
+            "[1, }",
+            "{\"a\": ]",
+        ];
+        for input in inputs.iter() {
+            let recovered = parse_json_recovering(input, 10);
+            assert_eq!(
+                recovered.errors.first(),
+                parse_json(input).err().as_ref(),
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn trailing_tokens_are_errors() {
+        let recovered = parse_json_recovering("[1] 2 3", 10);
+        assert_eq!(recovered.value, parse_json("[1]").unwrap());
+        let kinds: Vec<JsonErrorKind> = recovered.errors.into_iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                JsonErrorKind::TrailingToken(TokenKind::ValueNumber),
+                JsonErrorKind::TrailingToken(TokenKind::ValueNumber)
+            ]
+        );
+        let recovered = parse_json_recovering("1 2 3 4", 2);
+        assert_eq!(positions(&recovered.errors), vec![(1, 3), (1, 5)]);
+        let recovered = parse_json_recovering("{} @", 10);
+        assert_eq!(
+            recovered.errors[0].kind,
+            JsonErrorKind::UnexpectedCharacter {
+                found: '@',
+                expected: None
+            }
+        );
+    }
+
+    #[test]
+    fn depth_limit_stops_parsing() {
+        let input = "[".repeat(1000);
+        let recovered = parse_json_recovering(&input, 10);
+        assert_eq!(recovered.errors.len(), 1);
+        assert_eq!(
+            recovered.errors[0].kind,
+            JsonErrorKind::LimitExceeded(Limit::Depth(128))
+        );
+    }
+}
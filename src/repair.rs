@@ -0,0 +1,356 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Best-effort repair of malformed or truncated documents, such as the output
+//! of language models or of writers which crashed.
+//!
+//! The document is read with the JSON5 lexer, which already accepts unquoted
+//! keys, single quotes and comments, and with the recovering parser, whose
+//! resynchronization points give the fixes to apply.
+
+use crate::lexer::{Lexer, TokenKind};
+use crate::recovery::Recovery;
+use crate::{
+    BomPolicy, ColumnEncoding, Context, Dialect, Json, JsonErrorKind, LineIndex, ParseLimits,
+};
+use std::fmt;
+
+/// A fix applied by `repair_json`, and where it applies in the input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Repair {
+    pub kind: RepairKind,
+    pub context: Context,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RepairKind {
+    /// A markdown code fence was removed, with the text outside of it
+    RemovedCodeFence,
+    RemovedComment,
+    /// Text after the end of the document was removed
+    RemovedTrailingText,
+    /// A token with no place in the document was removed
+    RemovedToken(TokenKind),
+    /// Text which is not a token was removed, and replaced with `null` when a
+    /// value was expected
+    RemovedInvalidText(JsonErrorKind),
+    RemovedTrailingComma,
+    InsertedComma,
+    InsertedColon,
+    /// A missing value was replaced with `null`
+    InsertedNull,
+    /// A missing `]` or `}` was inserted
+    InsertedClosing(TokenKind),
+    /// A string cut by the end of the input was closed
+    ClosedString,
+    /// An object key without quotes was quoted
+    QuotedKey,
+    /// A token written with the JSON5 syntax, such as a single quoted string,
+    /// a hexadecimal number or a non-breaking space, was converted to JSON
+    ConvertedToken(TokenKind),
+    /// A literal JSON does not have was replaced: Python's `True`, `False` or
+    /// `None` with its JSON equivalent, and `NaN`, the infinities or a number
+    /// too large for `f64` with `null`
+    ReplacedLiteral(String),
+}
+
+impl fmt::Display for RepairKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RepairKind::RemovedCodeFence => write!(f, "Removed a markdown code fence"),
+            RepairKind::RemovedComment => write!(f, "Removed a comment"),
+            RepairKind::RemovedTrailingText => write!(f, "Removed the text after the document"),
+            RepairKind::RemovedToken(kind) => write!(f, "Removed {}", kind),
+            RepairKind::RemovedInvalidText(kind) => write!(f, "Removed invalid text: {}", kind),
+            RepairKind::RemovedTrailingComma => write!(f, "Removed a trailing comma"),
+            RepairKind::InsertedComma => write!(f, "Inserted a missing ','"),
+            RepairKind::InsertedColon => write!(f, "Inserted a missing ':'"),
+            RepairKind::InsertedNull => write!(f, "Inserted null for a missing value"),
+            RepairKind::InsertedClosing(kind) => write!(f, "Inserted a missing {}", kind),
+            RepairKind::ClosedString => write!(f, "Closed an unterminated string"),
+            RepairKind::QuotedKey => write!(f, "Quoted an object key"),
+            RepairKind::ConvertedToken(kind) => write!(f, "Converted {} to JSON", kind),
+            RepairKind::ReplacedLiteral(name) => write!(f, "Replaced the literal {}", name),
+        }
+    }
+}
+
+/// Parse the input whatever its errors, returning the value with the fixes
+/// which were needed, sorted by position.
+///
+/// Unclosed strings, arrays and objects are closed, missing separators are
+/// inserted, missing values become `null`, and invalid text is dropped. The
+/// JSON5 syntax is accepted as well, and reported as repairs.
+pub fn repair_json(input: &str) -> (Json, Vec<Repair>) {
+    repair_json_with_limits(input, ParseLimits::default())
+}
+
+/// Same as `repair_json`, reading the input within `limits`. The value read
+/// before an exceeded limit is returned.
+pub fn repair_json_with_limits(input: &str, limits: ParseLimits) -> (Json, Vec<Repair>) {
+    let mut repairs = Vec::new();
+    let fenced = strip_code_fence(input);
+    let text = match &fenced {
+        Some((text, fences)) => {
+            let index = LineIndex::new(input);
+            for &offset in fences {
+                repairs.push(Repair {
+                    kind: RepairKind::RemovedCodeFence,
+                    // The fences are found at character boundaries
+                    context: index.context(offset, ColumnEncoding::Chars).unwrap(),
+                });
+            }
+            text.as_str()
+        }
+        None => input,
+    };
+    let mut lexer = Lexer::new(text);
+    lexer.set_dialect(Dialect::Json5);
+    lexer.set_trivia(true);
+    let mut recovery = Recovery::new(lexer, limits, usize::MAX);
+    recovery.close_strings = true;
+    recovery.convert_json5 = true;
+    recovery.run();
+    repairs.append(&mut recovery.repairs);
+    repairs.sort_by_key(|repair| repair.context.offset);
+    (recovery.finish().value, repairs)
+}
+
+/// Blank out a markdown code fence and the text outside of it, keeping the
+/// offsets and the lines of the document. Returns the offsets of the fences.
+fn strip_code_fence(input: &str) -> Option<(String, Vec<usize>)> {
+    // A fence starts a line, so it cannot be inside a JSON string
+    let is_fence = |offset: usize| {
+        input[offset..].starts_with("```")
+            && input[..offset]
+                .rsplit('\n')
+                .next()
+                .unwrap_or("")
+                .trim()
+                .is_empty()
+    };
+    let open = input
+        .match_indices("```")
+        .map(|(i, _)| i)
+        .find(|&i| is_fence(i))?;
+    let start = open + input[open..].find('\n')? + 1;
+    let close = input[start..]
+        .match_indices("```")
+        .map(|(i, _)| start + i)
+        .find(|&i| is_fence(i));
+    let end = close.unwrap_or(input.len());
+    let bytes = input
+        .bytes()
+        .enumerate()
+        .map(|(i, b)| match b {
+            b'\n' => b'\n',
+            _ if (start..end).contains(&i) => b,
+            _ => b' ',
+        })
+        .collect();
+    // The kept range starts after a newline and ends before a fence
+    let text = String::from_utf8(bytes).unwrap();
+    Some((text, std::iter::once(open).chain(close).collect()))
+}
+
+/// Whether the text of a token read as JSON5 is valid JSON as well.
+pub(crate) fn is_json_token(raw: &str) -> bool {
+    match Lexer::new(raw).next() {
+        Some(Ok(token_info)) => token_info.end.offset == raw.len(),
+        _ => false,
+    }
+}
+
+/// Longest end of a string which can be an incomplete escape, a surrogate pair
+/// missing its last digit.
+const MAX_INCOMPLETE_ESCAPE: usize = 11;
+
+/// The content of a string cut by the end of the input, `raw` starting with
+/// its quote. An incomplete escape at the end is dropped.
+pub(crate) fn close_string(raw: &[u8]) -> String {
+    let quote = raw[0];
+    let shortest = usize::max(1, raw.len().saturating_sub(MAX_INCOMPLETE_ESCAPE));
+    for end in (shortest..=raw.len()).rev() {
+        let mut text = raw[..end].to_vec();
+        text.push(quote);
+        let mut lexer = Lexer::from_slice(&text, BomPolicy::Skip);
+        lexer.set_dialect(Dialect::Json5);
+        if let Some(Ok(token_info)) = lexer.next() {
+            if let Some(content) = token_info.token.key() {
+                return content;
+            }
+        }
+    }
+    String::from_utf8_lossy(&raw[1..]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    fn repair(input: &str) -> (Json, Vec<(RepairKind, usize, usize)>) {
+        let (value, repairs) = repair_json(input);
+        let repairs = repairs
+            .into_iter()
+            .map(|r| (r.kind, r.context.line, r.context.column))
+            .collect();
+        (value, repairs)
+    }
+
+    fn json(input: &str) -> Json {
+        parse_json(input).unwrap()
+    }
+
+    #[test]
+    fn valid_document_needs_no_repair() {
+        let input = r#"{"a": [1, 2.5, "three", null, true]}"#;
+        assert_eq!(repair(input), (json(input), vec![]));
+    }
+
+    #[test]
+    fn truncated_document() {
+        let (value, repairs) = repair(r#"{"a": [1, {"b": "unfinished \u00e"#);
+        assert_eq!(value, json(r#"{"a": [1, {"b": "unfinished "}]}"#));
+        assert_eq!(
+            repairs,
+            vec![
+                (RepairKind::ClosedString, 1, 34),
+                (RepairKind::InsertedClosing(TokenKind::ObjectEnd), 1, 34),
+                (RepairKind::InsertedClosing(TokenKind::ArrayEnd), 1, 34),
+                (RepairKind::InsertedClosing(TokenKind::ObjectEnd), 1, 34),
+            ]
+        );
+        assert_eq!(repair(r#"["a", "b"#).0, json(r#"["a", "b"]"#));
+        assert_eq!(repair(r#"{"a": "#).0, json(r#"{"a": null}"#));
+        assert_eq!(repair(r#"{"a"#).0, json(r#"{"a": null}"#));
+        assert_eq!(repair(r#"[1, 2,"#).0, json("[1, 2]"));
+    }
+
+    #[test]
+    fn unquoted_keys_and_python_literals() {
+        let (value, repairs) = repair("{ok: True, 'missing': None, \"no\": False}");
+        assert_eq!(value, json(r#"{"ok": true, "missing": null, "no": false}"#));
+        assert_eq!(
+            repairs,
+            vec![
+                (RepairKind::QuotedKey, 1, 2),
+                (RepairKind::ReplacedLiteral(String::from("True")), 1, 6),
+                (RepairKind::ConvertedToken(TokenKind::ValueString), 1, 12),
+                (RepairKind::ReplacedLiteral(String::from("None")), 1, 23),
+                (RepairKind::ReplacedLiteral(String::from("False")), 1, 35),
+            ]
+        );
+    }
+
+    #[test]
+    fn json5_is_converted() {
+        let input = "[0x1F, +1, .5, NaN, -Infinity, 1e999, '\\x41', /* c */ 2,\u{A0}3]";
+        let (value, repairs) = repair(input);
+        assert_eq!(value, json(r#"[31, 1, 0.5, null, null, null, "A", 2, 3]"#));
+        assert_eq!(
+            repairs,
+            vec![
+                (RepairKind::ConvertedToken(TokenKind::ValueNumber), 1, 2),
+                (RepairKind::ConvertedToken(TokenKind::ValueNumber), 1, 8),
+                (RepairKind::ConvertedToken(TokenKind::ValueNumber), 1, 12),
+                (RepairKind::ReplacedLiteral(String::from("NaN")), 1, 16),
+                (
+                    RepairKind::ReplacedLiteral(String::from("-Infinity")),
+                    1,
+                    21
+                ),
+                (RepairKind::ReplacedLiteral(String::from("1e999")), 1, 32),
+                (RepairKind::ConvertedToken(TokenKind::ValueString), 1, 39),
+                (RepairKind::RemovedComment, 1, 47),
+                (RepairKind::ConvertedToken(TokenKind::Whitespace), 1, 57),
+            ]
+        );
+        assert_eq!(value.to_string(), "[31,1,0.5,null,null,null,\"A\",2,3]");
+    }
+
+    #[test]
+    fn limits() {
+        let limits = ParseLimits {
+            max_depth: Some(2),
+            ..ParseLimits::default()
+        };
+        let (value, _) = repair_json_with_limits("[1, [2, [3]], 4]", limits);
+        assert_eq!(value, json("[1, [2]]"));
+    }
+
+    #[test]
+    fn code_fence() {
+        let input = "Here it is:\n```json\n{\"a\": [1, 2,]}\n```\nAnything else?";
+        let (value, repairs) = repair(input);
+        assert_eq!(value, json(r#"{"a": [1, 2]}"#));
+        assert_eq!(
+            repairs,
+            vec![
+                (RepairKind::RemovedCodeFence, 2, 1),
+                (RepairKind::RemovedTrailingComma, 3, 12),
+                (RepairKind::RemovedCodeFence, 4, 1),
+            ]
+        );
+        // Truncated before the closing fence
+        assert_eq!(repair("```\n[1, 2").0, json("[1, 2]"));
+        // Not a fence inside a string
+        assert_eq!(repair(r#"["```"]"#), (json(r#"["```"]"#), vec![]));
+    }
+
+    #[test]
+    fn separators_and_stray_tokens() {
+        let (value, repairs) = repair(r#"{"a" 1 "b": [1 2,, ]] x"#);
+        assert_eq!(value, json(r#"{"a": 1, "b": [1, 2, null]}"#));
+        assert_eq!(
+            repairs,
+            vec![
+                (RepairKind::InsertedColon, 1, 6),
+                (RepairKind::InsertedComma, 1, 8),
+                (RepairKind::InsertedComma, 1, 16),
+                (RepairKind::InsertedNull, 1, 18),
+                (RepairKind::RemovedTrailingComma, 1, 18),
+                (RepairKind::InsertedClosing(TokenKind::ObjectEnd), 1, 21),
+                (RepairKind::RemovedTrailingText, 1, 23),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_text_is_removed() {
+        let (value, repairs) = repair("[1, @, 3]");
+        assert_eq!(value, json("[1, null, 3]"));
+        assert_eq!(
+            repairs,
+            vec![(
+                RepairKind::RemovedInvalidText(JsonErrorKind::UnexpectedCharacter {
+                    found: '@',
+                    expected: None
+                }),
+                1,
+                5
+            )]
+        );
+        assert_eq!(repair("").0, Json::Null);
+        assert_eq!(repair("").1, vec![(RepairKind::InsertedNull, 1, 1)]);
+    }
+}
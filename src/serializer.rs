@@ -0,0 +1,281 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Compact serialization of `Json` values, through `Display`.
+//!
+//! Object members are written sorted by key so that the output is stable.
+//! Numbers which are not finite have no JSON representation and are written
+//! as `null`.
+//!
+//! `Json::as_json5` writes JSON5 instead, with unquoted keys when they are
+//! identifiers, and `Infinity` and `NaN` for the numbers which are not finite.
+
+use crate::lexer::json5::{is_identifier_part, is_identifier_start};
+use crate::{Json, Wtf8String};
+use std::fmt;
+
+/// Largest magnitude under which integral numbers are written without exponent.
+const MAX_PLAIN_INTEGER: f64 = 1e16;
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_value(f, self, false)
+    }
+}
+
+/// Compact JSON5 serialization of a `Json`, through `Display`.
+#[derive(Clone, Copy, Debug)]
+pub struct Json5Display<'a>(&'a Json);
+
+impl fmt::Display for Json5Display<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_value(f, self.0, true)
+    }
+}
+
+impl Json {
+    /// Display the value as JSON5 rather than JSON.
+    pub fn as_json5(&self) -> Json5Display<'_> {
+        Json5Display(self)
+    }
+}
+
+fn write_value(f: &mut fmt::Formatter<'_>, value: &Json, json5: bool) -> fmt::Result {
+    // Iterate with a stack, deeply nested values must not overflow
+    enum Item<'a> {
+        Value(&'a Json),
+        Raw(&'static str),
+        Key(&'a str),
+    }
+    let mut pending = vec![Item::Value(value)];
+    while let Some(item) = pending.pop() {
+        match item {
+            Item::Raw(text) => f.write_str(text)?,
+            Item::Key(key) => {
+                if json5 && is_identifier_name(key) {
+                    f.write_str(key)?;
+                } else {
+                    write_string(f, key)?;
+                }
+                f.write_str(":")?;
+            }
+            Item::Value(Json::Null) => f.write_str("null")?,
+            Item::Value(Json::Boolean(b)) => write!(f, "{}", b)?,
+            Item::Value(Json::Number(n)) if json5 && !n.is_finite() => write_json5_number(f, *n)?,
+            Item::Value(Json::Number(n)) => write_number(f, *n)?,
+            Item::Value(Json::CustomNumber(n)) => f.write_str(n.text())?,
+            Item::Value(Json::String(s)) => write_string(f, s)?,
+            Item::Value(Json::Wtf8String(s)) => write_wtf8_string(f, s)?,
+            Item::Value(Json::Array(vec)) => {
+                f.write_str("[")?;
+                pending.push(Item::Raw("]"));
+                for (idx, value) in vec.iter().enumerate().rev() {
+                    pending.push(Item::Value(value));
+                    if idx > 0 {
+                        pending.push(Item::Raw(","));
+                    }
+                }
+            }
+            Item::Value(Json::Object(map)) => {
+                f.write_str("{")?;
+                pending.push(Item::Raw("}"));
+                let mut members: Vec<_> = map.iter().collect();
+                members.sort_by(|a, b| a.0.cmp(b.0));
+                for (idx, (key, value)) in members.into_iter().enumerate().rev() {
+                    pending.push(Item::Value(value));
+                    pending.push(Item::Key(key));
+                    if idx > 0 {
+                        pending.push(Item::Raw(","));
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A key which JSON5 can write without quotes, an identifier for the lexer.
+/// Only ASCII keys are, so that readers with other Unicode versions agree.
+fn is_identifier_name(key: &str) -> bool {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii() && is_identifier_start(c) => (),
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii() && is_identifier_part(c))
+}
+
+fn write_json5_number(f: &mut dyn fmt::Write, n: f64) -> fmt::Result {
+    if n.is_nan() {
+        f.write_str("NaN")
+    } else if n > 0. {
+        f.write_str("Infinity")
+    } else {
+        f.write_str("-Infinity")
+    }
+}
+
+pub(crate) fn write_number(f: &mut dyn fmt::Write, n: f64) -> fmt::Result {
+    if !n.is_finite() {
+        f.write_str("null")
+    } else if n.fract() == 0. && n.abs() < MAX_PLAIN_INTEGER {
+        write!(f, "{}", n)
+    } else {
+        // Shortest representation which reads back to the same value
+        write!(f, "{:?}", n)
+    }
+}
+
+pub(crate) fn write_string(f: &mut dyn fmt::Write, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        write_string_char(f, c)?;
+    }
+    f.write_char('"')
+}
+
+/// Lone surrogates are written back as the escapes they were read from, with
+/// lowercase hexadecimal digits whatever their case in the input.
+pub(crate) fn write_wtf8_string(f: &mut dyn fmt::Write, s: &Wtf8String) -> fmt::Result {
+    f.write_char('"')?;
+    for code_point in s.code_points() {
+        match std::char::from_u32(code_point) {
+            Some(c) => write_string_char(f, c)?,
+            None => write!(f, "\\u{:04x}", code_point)?,
+        }
+    }
+    f.write_char('"')
+}
+
+fn write_string_char(f: &mut dyn fmt::Write, c: char) -> fmt::Result {
+    match c {
+        '"' => f.write_str("\\\""),
+        '\\' => f.write_str("\\\\"),
+        '\n' => f.write_str("\\n"),
+        '\r' => f.write_str("\\r"),
+        '\t' => f.write_str("\\t"),
+        '\u{0008}' => f.write_str("\\b"),
+        '\u{000C}' => f.write_str("\\f"),
+        c if c < '\u{0020}' => write!(f, "\\u{:04x}", c as u32),
+        c => f.write_char(c),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_json, parse_json_with_limits, ParseLimits};
+    use std::collections::HashMap;
+
+    #[test]
+    fn serialize_scalars() {
+        assert_eq!(Json::Null.to_string(), "null");
+        assert_eq!(Json::Boolean(true).to_string(), "true");
+        assert_eq!(Json::Number(-3.).to_string(), "-3");
+        assert_eq!(Json::Number(0.1).to_string(), "0.1");
+        assert_eq!(Json::Number(1e300).to_string(), "1e300");
+        assert_eq!(Json::Number(f64::NAN).to_string(), "null");
+        let s = Json::String(String::from("a\"b\\c\n\u{1}é"));
+        assert_eq!(s.to_string(), r#""a\"b\\c\n\u0001é""#);
+    }
+
+    #[test]
+    fn serialize_containers() {
+        let mut map = HashMap::new();
+        map.insert(String::from("b"), Json::Array(vec![]));
+        map.insert(
+            String::from("a"),
+            Json::Array(vec![Json::Number(1.), Json::Null]),
+        );
+        assert_eq!(Json::Object(map).to_string(), r#"{"a":[1,null],"b":[]}"#);
+    }
+
+    #[test]
+    fn serialize_round_trip() {
+        let input = r#"{"one": [1, 2.5, -1e-7, "🐱"], "two": {"three": true}}"#;
+        let json = parse_json(input).unwrap();
+        assert_eq!(parse_json(&json.to_string()).unwrap(), json);
+    }
+
+    #[test]
+    fn serialize_deeply_nested() {
+        let depth = 100_000;
+        let input = format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        let json = parse_json_with_limits(&input, ParseLimits::unlimited()).unwrap();
+        assert_eq!(json.to_string(), input);
+        json.drop_iteratively();
+    }
+
+    #[test]
+    fn lone_surrogates_round_trip() {
+        let input = r#"{"k": ["a\ud800\"b", "🐱", "\udc00"]}"#;
+        let mut lexer = crate::Lexer::new(input);
+        lexer.set_surrogate_policy(crate::SurrogatePolicy::Preserve);
+        let json = crate::Parser::new(lexer).unwrap().parse().unwrap();
+        assert_eq!(
+            json.to_string(),
+            "{\"k\":[\"a\\ud800\\\"b\",\"🐱\",\"\\udc00\"]}"
+        );
+        // The hexadecimal digits are written in lowercase
+        let mut lexer = crate::Lexer::new(r#""\uDC00""#);
+        lexer.set_surrogate_policy(crate::SurrogatePolicy::Preserve);
+        let json = crate::Parser::new(lexer).unwrap().parse().unwrap();
+        assert_eq!(json.to_string(), r#""\udc00""#);
+    }
+
+    #[test]
+    fn serialize_json5() {
+        let mut map = HashMap::new();
+        map.insert(String::from("plain_$1"), Json::Number(f64::INFINITY));
+        map.insert(String::from("with space"), Json::Number(f64::NEG_INFINITY));
+        map.insert(String::from("1st"), Json::Number(f64::NAN));
+        map.insert(String::from("null"), Json::Number(1.5));
+        let json = Json::Object(map);
+        assert_eq!(
+            json.as_json5().to_string(),
+            r#"{"1st":NaN,null:1.5,plain_$1:Infinity,"with space":-Infinity}"#
+        );
+        assert_eq!(
+            json.to_string(),
+            r#"{"1st":null,"null":1.5,"plain_$1":null,"with space":null}"#
+        );
+        // Not all alphabetic characters start an identifier
+        let mut map = HashMap::new();
+        map.insert(String::from("\u{345}"), Json::Null);
+        assert_eq!(
+            Json::Object(map).as_json5().to_string(),
+            "{\"\u{345}\":null}"
+        );
+    }
+
+    #[test]
+    fn json5_round_trip() {
+        let input = "{a: [1, -Infinity, 'é\\n'], 'b c': {Infinity: null}}";
+        let json = crate::parse_json_with_dialect(input, crate::Dialect::Json5).unwrap();
+        let output = json.as_json5().to_string();
+        assert_eq!(output, r#"{a:[1,-Infinity,"é\n"],"b c":{Infinity:null}}"#);
+        assert_eq!(
+            crate::parse_json_with_dialect(&output, crate::Dialect::Json5).unwrap(),
+            json
+        );
+    }
+}
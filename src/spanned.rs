@@ -0,0 +1,353 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Values which remember where they were in the input, to report problems
+//! found after parsing.
+
+use crate::grammar::Builder;
+use crate::lexer::{Lexer, Token, TokenInfo};
+use crate::parser::Parser;
+use crate::{Context, CustomNumber, Json, JsonError, ParseLimits, Wtf8String};
+use std::collections::HashMap;
+use std::slice;
+
+/// Start and end positions of a value or key, the end being just after it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Span {
+    pub start: Context,
+    pub end: Context,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Span,
+}
+
+/// Like `Json`, but with the span of every value and key. Object members are
+/// kept in input order, duplicates included.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpannedJson {
+    Object(Vec<(Spanned<String>, Spanned<SpannedJson>)>),
+    Array(Vec<Spanned<SpannedJson>>),
+    String(String),
+    Wtf8String(Wtf8String),
+    Number(f64),
+    CustomNumber(CustomNumber),
+    Boolean(bool),
+    Null,
+}
+
+impl Spanned<SpannedJson> {
+    /// The value of the last member with this key, as `Json` would keep it.
+    pub fn get(&self, key: &str) -> Option<&Spanned<SpannedJson>> {
+        match &self.value {
+            SpannedJson::Object(members) => members
+                .iter()
+                .rev()
+                .find(|(candidate, _)| candidate.value == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn index(&self, index: usize) -> Option<&Spanned<SpannedJson>> {
+        match &self.value {
+            SpannedJson::Array(vec) => vec.get(index),
+            _ => None,
+        }
+    }
+
+    /// Convert to a `Json` value, dropping the spans.
+    pub fn to_json(&self) -> Json {
+        // Iterate with a stack, deeply nested values must not overflow
+        enum Frame<'a> {
+            Array(slice::Iter<'a, Spanned<SpannedJson>>, Vec<Json>),
+            Object(slice::Iter<'a, Member>, HashMap<String, Json>, &'a str),
+        }
+        let mut stack = Vec::new();
+        let mut value = self;
+        loop {
+            let mut done = match &value.value {
+                SpannedJson::Array(vec) => {
+                    stack.push(Frame::Array(vec.iter(), Vec::new()));
+                    None
+                }
+                SpannedJson::Object(members) => {
+                    stack.push(Frame::Object(members.iter(), HashMap::new(), ""));
+                    None
+                }
+                SpannedJson::String(s) => Some(Json::String(s.clone())),
+                SpannedJson::Wtf8String(s) => Some(Json::Wtf8String(s.clone())),
+                SpannedJson::Number(n) => Some(Json::Number(*n)),
+                SpannedJson::CustomNumber(n) => Some(Json::CustomNumber(n.clone())),
+                SpannedJson::Boolean(b) => Some(Json::Boolean(*b)),
+                SpannedJson::Null => Some(Json::Null),
+            };
+            // Give the converted value to its container, and close the
+            // containers which have no value left
+            loop {
+                let closed = match stack.last_mut() {
+                    None => return done.expect("The root is converted"),
+                    Some(Frame::Array(elements, vec)) => {
+                        vec.extend(done.take());
+                        match elements.next() {
+                            Some(element) => {
+                                value = element;
+                                break;
+                            }
+                            None => Json::Array(std::mem::take(vec)),
+                        }
+                    }
+                    Some(Frame::Object(members, map, key)) => {
+                        if let Some(done) = done.take() {
+                            map.insert(key.to_string(), done);
+                        }
+                        match members.next() {
+                            Some((member_key, member_value)) => {
+                                *key = &member_key.value;
+                                value = member_value;
+                                break;
+                            }
+                            None => Json::Object(std::mem::take(map)),
+                        }
+                    }
+                };
+                stack.pop();
+                done = Some(closed);
+            }
+        }
+    }
+}
+
+/// Parse with the default limits, see `ParseLimits`.
+pub fn parse_json_spanned(input: &str) -> Result<Spanned<SpannedJson>, JsonError> {
+    parse_json_spanned_with_limits(input, ParseLimits::default())
+}
+
+pub fn parse_json_spanned_with_limits(
+    input: &str,
+    limits: ParseLimits,
+) -> Result<Spanned<SpannedJson>, JsonError> {
+    let lexer = Lexer::new(input);
+    let mut parser = Parser::with_limits(lexer, limits)?;
+    parser.parse_spanned()
+}
+
+/// Builds `SpannedJson` values, containers keeping their start, and an object
+/// the key of the member being read.
+struct SpannedBuilder;
+
+type Member = (Spanned<String>, Spanned<SpannedJson>);
+
+fn spanned<T>(value: T, start: &Context, end: &Context) -> Spanned<T> {
+    Spanned {
+        value,
+        span: Span {
+            start: start.clone(),
+            end: end.clone(),
+        },
+    }
+}
+
+impl Builder for SpannedBuilder {
+    type Value = Spanned<SpannedJson>;
+    type Array = (Context, Vec<Spanned<SpannedJson>>);
+    type Object = (Context, Vec<Member>, Option<Spanned<String>>);
+
+    fn scalar(&mut self, token_info: &TokenInfo) -> Self::Value {
+        let value = match &token_info.token {
+            Token::ValueNumber(n) => SpannedJson::Number(*n),
+            Token::ValueCustomNumber(n) => SpannedJson::CustomNumber(n.clone()),
+            Token::ValueBoolean(b) => SpannedJson::Boolean(*b),
+            Token::ValueString(s) => SpannedJson::String(s.to_string()),
+            Token::ValueWtf8String(s) => SpannedJson::Wtf8String(s.clone()),
+            _ => SpannedJson::Null,
+        };
+        spanned(value, &token_info.context, &token_info.end)
+    }
+
+    fn start_array(&mut self, open: &TokenInfo) -> Self::Array {
+        (open.context.clone(), Vec::new())
+    }
+
+    fn push_element(&mut self, array: &mut Self::Array, value: Self::Value) {
+        array.1.push(value);
+    }
+
+    fn end_array(&mut self, array: Self::Array, close: &TokenInfo) -> Self::Value {
+        spanned(SpannedJson::Array(array.1), &array.0, &close.end)
+    }
+
+    fn start_object(&mut self, open: &TokenInfo) -> Self::Object {
+        (open.context.clone(), Vec::new(), None)
+    }
+
+    fn key(&mut self, object: &mut Self::Object, key: String, token_info: &TokenInfo) {
+        object.2 = Some(spanned(key, &token_info.context, &token_info.end));
+    }
+
+    fn push_member(&mut self, object: &mut Self::Object, value: Self::Value) {
+        if let Some(key) = object.2.take() {
+            object.1.push((key, value));
+        }
+    }
+
+    fn end_object(&mut self, object: Self::Object, close: &TokenInfo) -> Self::Value {
+        spanned(SpannedJson::Object(object.1), &object.0, &close.end)
+    }
+}
+
+impl<'a> Parser<'a> {
+    /// Same as `parse`, but keeping the span of every value and key.
+    pub fn parse_spanned(&mut self) -> Result<Spanned<SpannedJson>, JsonError> {
+        self.parse_with(SpannedBuilder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_json;
+
+    const CONFIG: &str = "{\n  \"name\": \"demo\",\n  \"server\": {\n    \"port\": \"8080\"\n  },\n  \"tags\": [1, [true]]\n}";
+
+    /// Dismantle a value without recursion, deep values would overflow the
+    /// stack of the recursive `Drop`.
+    fn drop_iteratively(value: Spanned<SpannedJson>) {
+        let mut pending = vec![value];
+        while let Some(value) = pending.pop() {
+            match value.value {
+                SpannedJson::Array(vec) => pending.extend(vec),
+                SpannedJson::Object(members) => {
+                    pending.extend(members.into_iter().map(|(_, value)| value))
+                }
+                _ => (),
+            }
+        }
+    }
+
+    #[test]
+    fn deeply_nested_to_json() {
+        let depth = 100_000;
+        let input = format!("{}{}", r#"{"a":["#.repeat(depth), "]}".repeat(depth));
+        let root = parse_json_spanned_with_limits(&input, ParseLimits::unlimited()).unwrap();
+        let json = root.to_json();
+        assert_eq!(json.to_string(), input);
+        json.drop_iteratively();
+        drop_iteratively(root);
+    }
+
+    #[test]
+    fn spans_of_values_and_keys() {
+        let root = parse_json_spanned(CONFIG).unwrap();
+        assert_eq!(root.span.start, Context::default());
+        assert_eq!(root.span.end.offset, CONFIG.len());
+        let port = root.get("server").unwrap().get("port").unwrap();
+        assert_eq!(port.value, SpannedJson::String(String::from("8080")));
+        assert_eq!(
+            &CONFIG[port.span.start.offset..port.span.end.offset],
+            "\"8080\""
+        );
+        assert_eq!(
+            port.span.start,
+            Context {
+                line: 4,
+                column: 13,
+                offset: 46
+            }
+        );
+        let server = root.get("server").unwrap();
+        assert_eq!(
+            &CONFIG[server.span.start.offset..server.span.end.offset],
+            "{\n    \"port\": \"8080\"\n  }"
+        );
+        match &root.value {
+            SpannedJson::Object(members) => {
+                let (key, _) = &members[2];
+                assert_eq!(key.value, "tags");
+                assert_eq!(
+                    &CONFIG[key.span.start.offset..key.span.end.offset],
+                    "\"tags\""
+                );
+            }
+            _ => panic!("Should be an object"),
+        }
+        let inner = root.get("tags").unwrap().index(1).unwrap();
+        assert_eq!(
+            &CONFIG[inner.span.start.offset..inner.span.end.offset],
+            "[true]"
+        );
+    }
+
+    #[test]
+    fn same_value_as_parse_json() {
+        let inputs = [
+            CONFIG,
+            "[]",
+            "{}",
+            "[{\"a\": [1, {}]}, \"é\", -1.5e3]",
+            "{\"a\": 1, \"a\": 2}",
+        ];
+        for input in inputs.iter() {
+            let spanned = parse_json_spanned(input).unwrap();
+            assert_eq!(spanned.to_json(), parse_json(input).unwrap());
+        }
+    }
+
+    #[test]
+    fn duplicate_keys_are_kept() {
+        let root = parse_json_spanned("{\"a\": 1, \"a\": 2}").unwrap();
+        match &root.value {
+            SpannedJson::Object(members) => assert_eq!(members.len(), 2),
+            _ => panic!("Should be an object"),
+        }
+        assert_eq!(root.get("a").unwrap().value, SpannedJson::Number(2.));
+    }
+
+    #[test]
+    fn same_errors_as_parse_json() {
+        for input in [
+            "[1 2]",
+            "{\"a\" 1}",
+            "{\"a\": 1,}",
+            "",
+            "[1, {\"b\": ",
+            "[[[[",
+        ]
+        .iter()
+        {
+            assert_eq!(parse_json_spanned(input).err(), parse_json(input).err());
+        }
+    }
+
+    #[test]
+    fn jsonc_is_parsed_with_spans() {
+        let input = "[1, /* two */ 2,]";
+        let mut lexer = Lexer::new(input);
+        lexer.set_dialect(crate::Dialect::Jsonc);
+        let root = Parser::new(lexer).unwrap().parse_spanned().unwrap();
+        let two = root.index(1).unwrap();
+        assert_eq!(two.span.start.offset, 14);
+        assert!(root.index(2).is_none());
+    }
+}
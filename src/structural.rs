@@ -0,0 +1,446 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! First stage of a simdjson-style parsing: the input is classified in blocks of
+//! 64 bytes, and the offsets where a token may start are collected in an index.
+//!
+//! The index contains the structural characters `{}[]:,` outside of strings, the
+//! opening quotes of strings, and the first byte of any other sequence following
+//! a whitespace or a structural character (literals, numbers, invalid data).
+//!
+//! Experimental: only `Lexer::with_structural_index` uses the index.
+
+use std::convert::TryInto;
+
+const BLOCK_SIZE: usize = 64;
+
+/// Bit masks for one block of 64 bytes, bit `i` standing for the byte `i`.
+#[derive(Debug, Default, PartialEq)]
+struct BlockMasks {
+    backslash: u64,
+    quote: u64,
+    structural: u64,
+    whitespace: u64,
+}
+
+type Classifier = fn(&[u8; BLOCK_SIZE]) -> BlockMasks;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Implementation {
+    Scalar,
+    Sse2,
+    Avx2,
+}
+
+impl Implementation {
+    /// The fastest implementation supported by the running CPU.
+    pub fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") {
+                return Implementation::Avx2;
+            }
+            if is_x86_feature_detected!("sse2") {
+                return Implementation::Sse2;
+            }
+        }
+        Implementation::Scalar
+    }
+
+    fn is_supported(self) -> bool {
+        match self {
+            Implementation::Scalar => true,
+            #[cfg(target_arch = "x86_64")]
+            Implementation::Sse2 => is_x86_feature_detected!("sse2"),
+            #[cfg(target_arch = "x86_64")]
+            Implementation::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(not(target_arch = "x86_64"))]
+            _ => false,
+        }
+    }
+
+    fn classifier(self) -> Classifier {
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            Implementation::Sse2 => x86::classify_sse2,
+            #[cfg(target_arch = "x86_64")]
+            Implementation::Avx2 => x86::classify_avx2,
+            _ => classify_scalar,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StructuralIndex {
+    positions: Vec<u32>,
+}
+
+impl StructuralIndex {
+    /// Build the index with the fastest implementation available at runtime.
+    ///
+    /// Returns `None` if the data is too big for the offsets to fit in a `u32`.
+    pub fn new(data: &[u8]) -> Option<Self> {
+        Self::with_implementation(data, Implementation::detect())
+    }
+
+    /// Build the index with a given implementation, falling back to the scalar
+    /// one if it is not supported by the running CPU.
+    pub fn with_implementation(data: &[u8], implementation: Implementation) -> Option<Self> {
+        if data.len() > u32::MAX as usize {
+            return None;
+        }
+        let implementation = if implementation.is_supported() {
+            implementation
+        } else {
+            Implementation::Scalar
+        };
+        Some(build_index(data, implementation.classifier()))
+    }
+
+    pub fn positions(&self) -> &[u32] {
+        &self.positions
+    }
+}
+
+fn build_index(data: &[u8], classify: Classifier) -> StructuralIndex {
+    let mut positions = Vec::with_capacity(data.len() / 8);
+    let mut escape_carry = false;
+    let mut in_string_carry = 0u64;
+    let mut separator_carry = 1u64;
+    for (block_idx, chunk) in data.chunks(BLOCK_SIZE).enumerate() {
+        let masks = if chunk.len() == BLOCK_SIZE {
+            classify(chunk.try_into().unwrap())
+        } else {
+            // Pad the last block with whitespace
+            let mut block = [b' '; BLOCK_SIZE];
+            block[..chunk.len()].copy_from_slice(chunk);
+            classify(&block)
+        };
+        let escaped = escaped_mask(masks.backslash, &mut escape_carry);
+        let quote = masks.quote & !escaped;
+        let in_string = prefix_xor(quote) ^ in_string_carry;
+        in_string_carry = 0u64.wrapping_sub(in_string >> 63);
+        let opening_quote = quote & in_string;
+        let structural = masks.structural & !in_string;
+        let separator = structural | (masks.whitespace & !in_string);
+        let follows_separator = (separator << 1) | separator_carry;
+        separator_carry = separator >> 63;
+        let scalar = !(separator | quote | in_string);
+        let mut starts = structural | opening_quote | (scalar & follows_separator);
+        if chunk.len() < BLOCK_SIZE {
+            starts &= (1u64 << chunk.len()) - 1;
+        }
+        let base = (block_idx * BLOCK_SIZE) as u32;
+        while starts != 0 {
+            positions.push(base + starts.trailing_zeros());
+            starts &= starts - 1;
+        }
+    }
+    StructuralIndex { positions }
+}
+
+/// Bits of the bytes preceded by an odd number of consecutive backslashes.
+fn escaped_mask(backslash: u64, carry: &mut bool) -> u64 {
+    let mut escaped = 0;
+    let mut backslash = backslash;
+    if *carry {
+        escaped |= 1;
+        backslash &= !1;
+    }
+    *carry = false;
+    while backslash != 0 {
+        let bit = backslash.trailing_zeros();
+        backslash &= backslash - 1;
+        if bit == 63 {
+            *carry = true;
+        } else {
+            escaped |= 1 << (bit + 1);
+            backslash &= !(1 << (bit + 1));
+        }
+    }
+    escaped
+}
+
+/// Each bit becomes the parity of the bits up to and including itself.
+fn prefix_xor(mut bitmask: u64) -> u64 {
+    bitmask ^= bitmask << 1;
+    bitmask ^= bitmask << 2;
+    bitmask ^= bitmask << 4;
+    bitmask ^= bitmask << 8;
+    bitmask ^= bitmask << 16;
+    bitmask ^= bitmask << 32;
+    bitmask
+}
+
+fn classify_scalar(block: &[u8; BLOCK_SIZE]) -> BlockMasks {
+    let mut masks = BlockMasks::default();
+    for (i, &b) in block.iter().enumerate() {
+        let bit = 1 << i;
+        match b {
+            b'\\' => masks.backslash |= bit,
+            b'"' => masks.quote |= bit,
+            b'{' | b'}' | b'[' | b']' | b':' | b',' => masks.structural |= bit,
+            b' ' | b'\t' | b'\r' | b'\n' => masks.whitespace |= bit,
+            _ => (),
+        }
+    }
+    masks
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::{BlockMasks, BLOCK_SIZE};
+    use std::arch::x86_64::*;
+
+    pub fn classify_sse2(block: &[u8; BLOCK_SIZE]) -> BlockMasks {
+        // SAFETY: only selected after checking the CPU supports SSE2
+        unsafe { classify_sse2_impl(block) }
+    }
+
+    pub fn classify_avx2(block: &[u8; BLOCK_SIZE]) -> BlockMasks {
+        // SAFETY: only selected after checking the CPU supports AVX2
+        unsafe { classify_avx2_impl(block) }
+    }
+
+    #[target_feature(enable = "sse2")]
+    unsafe fn classify_sse2_impl(block: &[u8; BLOCK_SIZE]) -> BlockMasks {
+        let mut masks = BlockMasks::default();
+        for lane in 0..4 {
+            // SAFETY: the block has 64 bytes, each lane reads 16 of them
+            let v = unsafe { _mm_loadu_si128(block.as_ptr().add(lane * 16) as *const __m128i) };
+            let eq = |c: u8| _mm_cmpeq_epi8(v, _mm_set1_epi8(c as i8));
+            let to_mask = |m: __m128i| (_mm_movemask_epi8(m) as u16 as u64) << (lane * 16);
+            masks.backslash |= to_mask(eq(b'\\'));
+            masks.quote |= to_mask(eq(b'"'));
+            let structural = _mm_or_si128(
+                _mm_or_si128(
+                    _mm_or_si128(eq(b'{'), eq(b'}')),
+                    _mm_or_si128(eq(b'['), eq(b']')),
+                ),
+                _mm_or_si128(eq(b':'), eq(b',')),
+            );
+            masks.structural |= to_mask(structural);
+            let whitespace = _mm_or_si128(
+                _mm_or_si128(eq(b' '), eq(b'\t')),
+                _mm_or_si128(eq(b'\r'), eq(b'\n')),
+            );
+            masks.whitespace |= to_mask(whitespace);
+        }
+        masks
+    }
+
+    #[target_feature(enable = "avx2")]
+    unsafe fn classify_avx2_impl(block: &[u8; BLOCK_SIZE]) -> BlockMasks {
+        let mut masks = BlockMasks::default();
+        for lane in 0..2 {
+            // SAFETY: the block has 64 bytes, each lane reads 32 of them
+            let v = unsafe { _mm256_loadu_si256(block.as_ptr().add(lane * 32) as *const __m256i) };
+            let eq = |c: u8| _mm256_cmpeq_epi8(v, _mm256_set1_epi8(c as i8));
+            let to_mask = |m: __m256i| (_mm256_movemask_epi8(m) as u32 as u64) << (lane * 32);
+            masks.backslash |= to_mask(eq(b'\\'));
+            masks.quote |= to_mask(eq(b'"'));
+            let structural = _mm256_or_si256(
+                _mm256_or_si256(
+                    _mm256_or_si256(eq(b'{'), eq(b'}')),
+                    _mm256_or_si256(eq(b'['), eq(b']')),
+                ),
+                _mm256_or_si256(eq(b':'), eq(b',')),
+            );
+            masks.structural |= to_mask(structural);
+            let whitespace = _mm256_or_si256(
+                _mm256_or_si256(eq(b' '), eq(b'\t')),
+                _mm256_or_si256(eq(b'\r'), eq(b'\n')),
+            );
+            masks.whitespace |= to_mask(whitespace);
+        }
+        masks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lexer;
+
+    const CORPUS: &[&str] = &[
+        "",
+        "   ",
+        "null",
+        r#" {"one": 1, "two": {"table":[1, null, true, {"bloup": 3}]}} "#,
+        r#"["a\"b", "c\\", "\\\"", "\\\\\"d"]"#,
+        r#"[-12.34e+5, 0.5 ,1E3,-0]"#,
+        r#""go: 碁, cat: 🐱" "\ud83d\udc31""#,
+        "[1 2 truex nul \"unterminated",
+        "{\"key\"\n:\r\n\t\"value\"}\n",
+        "[\"\u{1}\", 2]",
+        "[é, \\, 3]",
+        "\"\\",
+        r#"{"a":"\"", "b" : [ "\\" ] }"#,
+    ];
+
+    fn lexer_output(lexer: Lexer) -> Vec<String> {
+        lexer
+            .map(|result| match result {
+                Ok(token_info) => format!(
+                    "{:?} {}:{}",
+                    token_info.token, token_info.context.line, token_info.context.column
+                ),
+                Err(error) => format!("{}", error),
+            })
+            .collect()
+    }
+
+    fn assert_same_as_scalar_lexer(input: &str) {
+        let expected = lexer_output(Lexer::new(input));
+        for &implementation in &[
+            Implementation::Scalar,
+            Implementation::Sse2,
+            Implementation::Avx2,
+        ] {
+            let lexer = Lexer::with_structural_implementation(input, implementation);
+            let candidate = lexer_output(lexer.unwrap());
+            assert_eq!(candidate, expected, "input: {:?}", input);
+        }
+    }
+
+    /// Small xorshift generator, so the fuzz corpus is reproducible.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+    }
+
+    #[test]
+    fn prefix_xor_marks_string_content() {
+        assert_eq!(prefix_xor(0b1001), 0b0111);
+        assert_eq!(prefix_xor(1 << 63), 1 << 63);
+    }
+
+    #[test]
+    fn escaped_backslash_sequences() {
+        let mut carry = false;
+        assert_eq!(escaped_mask(0b0110, &mut carry), 0b0100);
+        assert_eq!(escaped_mask(0b0111, &mut carry), 0b1010);
+        assert!(!carry);
+        assert_eq!(escaped_mask(1 << 63, &mut carry), 0);
+        assert!(carry);
+        assert_eq!(escaped_mask(0b1, &mut carry), 0b1);
+        assert!(!carry);
+    }
+
+    #[test]
+    fn implementations_classify_identically() {
+        let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+        for _ in 0..1000 {
+            let mut block = [0u8; BLOCK_SIZE];
+            for b in block.iter_mut() {
+                let alphabet = b" \t\r\n\"\\{}[]:,a0\x80\xff";
+                *b = alphabet[(rng.next() % alphabet.len() as u64) as usize];
+            }
+            let expected = classify_scalar(&block);
+            for &implementation in &[Implementation::Sse2, Implementation::Avx2] {
+                if implementation.is_supported() {
+                    assert_eq!(implementation.classifier()(&block), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn index_positions() {
+        let index = StructuralIndex::new(br#" {"a,b": [1, tr ue]}"#).unwrap();
+        assert_eq!(index.positions(), &[1, 2, 7, 9, 10, 11, 13, 16, 18, 19]);
+    }
+
+    #[test]
+    fn index_positions_across_blocks() {
+        let input = format!("[{}\"{}\\\"\", 1]", " ".repeat(60), "x".repeat(70));
+        let index = StructuralIndex::new(input.as_bytes()).unwrap();
+        assert_eq!(index.positions(), &[0, 61, 135, 137, 138]);
+    }
+
+    #[test]
+    fn differential_corpus() {
+        for input in CORPUS {
+            assert_same_as_scalar_lexer(input);
+        }
+    }
+
+    #[test]
+    fn differential_corpus_across_blocks() {
+        for input in CORPUS {
+            for shift in [1, 31, 63, 64, 65].iter() {
+                assert_same_as_scalar_lexer(&format!("{}{}", " ".repeat(*shift), input));
+                assert_same_as_scalar_lexer(&format!("[{}{}]", "\\".repeat(*shift), input));
+            }
+        }
+    }
+
+    #[test]
+    fn differential_fuzz() {
+        const FRAGMENTS: &[&str] = &[
+            " ",
+            "\n",
+            "\t",
+            "{",
+            "}",
+            "[",
+            "]",
+            ":",
+            ",",
+            "\"",
+            "\\",
+            "\\\"",
+            "\\\\",
+            "\\u00e9",
+            "\\ud83d\\udc31",
+            "a",
+            "é",
+            "🐱",
+            "null",
+            "true",
+            "false",
+            "nu",
+            "-",
+            "0",
+            "12",
+            ".5",
+            "e+3",
+            "E",
+            "\u{1}",
+            "\u{7f}",
+        ];
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+        for _ in 0..2000 {
+            let len = rng.next() % 200;
+            let input: String = (0..len)
+                .map(|_| FRAGMENTS[(rng.next() % FRAGMENTS.len() as u64) as usize])
+                .collect();
+            assert_same_as_scalar_lexer(&input);
+        }
+    }
+}
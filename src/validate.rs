@@ -0,0 +1,167 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Validation of a document without building its value, for when only the
+//! verdict matters.
+
+use crate::grammar::Builder;
+use crate::lexer::{Lexer, TokenInfo};
+use crate::parser::Parser;
+use crate::{JsonError, ParseLimits};
+
+/// Check that `input` is valid JSON, with the same verdict and error as
+/// `parse_json`, but without allocating strings, maps or values.
+pub fn validate(input: &str) -> Result<(), JsonError> {
+    validate_with_limits(input, ParseLimits::default())
+}
+
+pub fn validate_with_limits(input: &str, limits: ParseLimits) -> Result<(), JsonError> {
+    let mut lexer = Lexer::new(input);
+    lexer.set_discard_strings(true);
+    let mut parser = Parser::with_limits(lexer, limits)?;
+    parser.validate()
+}
+
+/// Builds nothing, the grammar alone checks the document.
+struct Validator;
+
+impl Builder for Validator {
+    type Value = ();
+    type Array = ();
+    type Object = ();
+
+    fn scalar(&mut self, _token_info: &TokenInfo) {}
+
+    fn start_array(&mut self, _open: &TokenInfo) {}
+
+    fn push_element(&mut self, _array: &mut (), _value: ()) {}
+
+    fn end_array(&mut self, _array: (), _close: &TokenInfo) {}
+
+    fn start_object(&mut self, _open: &TokenInfo) {}
+
+    fn key(&mut self, _object: &mut (), _key: String, _token_info: &TokenInfo) {}
+
+    fn push_member(&mut self, _object: &mut (), _value: ()) {}
+
+    fn end_object(&mut self, _object: (), _close: &TokenInfo) {}
+}
+
+impl<'a> Parser<'a> {
+    /// Same as `parse`, but only checking the tokens. The strings are still
+    /// built by the lexer unless it was created by `validate`.
+    pub(crate) fn validate(&mut self) -> Result<(), JsonError> {
+        self.parse_with(Validator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_json, parse_json_with_limits, JsonErrorKind, Limit};
+
+    /// Documents which `parse_json` accepts or rejects in various ways.
+    const SAMPLES: &[&str] = &[
+        "",
+        " ",
+        "null",
+        "[1, 2.5e3, -0.25, 1e400, 12345678901234567890]",
+        r#"{"a": [true, false, null], "b": {"c": "d"}, "a": 1}"#,
+        r#""é😀\n\"\\""#,
+        r#""\ud83d""#,
+        r#""\udc00x""#,
+        r#""\x""#,
+        r#""\u12""#,
+        "\"\u{1}\"",
+        "\"abc",
+        "[1, 2",
+        "[1 2]",
+        "[1,]",
+        "{\"a\" 1}",
+        "{\"a\": 1,}",
+        "{1: 2}",
+        "[-]",
+        "[01]",
+        "[1.]",
+        "[.5]",
+        "[1e]",
+        "[tru]",
+        "[nul]",
+        "{\"a\":}",
+        "]",
+        "[[[[]]]]",
+        "[\"\u{e9}\u{1F600}\"]",
+    ];
+
+    #[test]
+    fn same_verdict_as_parse_json() {
+        for input in SAMPLES {
+            assert_eq!(
+                validate(input),
+                parse_json(input).map(|_| ()),
+                "input: {:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn same_limits_as_parse_json() {
+        let limits = ParseLimits {
+            max_depth: Some(2),
+            max_string_length: Some(3),
+            max_container_elements: Some(2),
+            max_total_values: Some(5),
+            ..ParseLimits::unlimited()
+        };
+        let inputs = [
+            "[[1]]",
+            "[[[1]]]",
+            r#"["abc"]"#,
+            r#"["aé"]"#,
+            r#"{"abcd": 1}"#,
+            "[1, 2, 3]",
+            r#"{"a": 1, "b": 2, "c": 3}"#,
+            r#"{"a": 1, "a": 2, "a": 3}"#,
+            r#"{"a": 1, "a": 2}"#,
+            "[[1, 2], [3, 4]]",
+        ];
+        for input in inputs.iter() {
+            assert_eq!(
+                validate_with_limits(input, limits.clone()),
+                parse_json_with_limits(input, limits.clone()).map(|_| ()),
+                "input: {:?}",
+                input
+            );
+        }
+        let error = validate_with_limits("[[[1]]]", limits).unwrap_err();
+        assert_eq!(error.kind, JsonErrorKind::LimitExceeded(Limit::Depth(2)));
+        assert_eq!(error.context.column, 3);
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let input = "[".repeat(100_000);
+        let error = validate(&input).unwrap_err();
+        assert_eq!(error.kind, JsonErrorKind::LimitExceeded(Limit::Depth(128)));
+    }
+}
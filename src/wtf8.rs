@@ -0,0 +1,187 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! Strings which may contain lone surrogates, see `SurrogatePolicy::Preserve`.
+
+use std::fmt;
+
+/// What the lexer does with a `\u` escape of a UTF-16 surrogate which is not
+/// part of a valid pair, as JavaScript may produce when cutting strings.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SurrogatePolicy {
+    /// Report an invalid unicode escape
+    #[default]
+    Strict,
+    /// Replace the lone surrogate with U+FFFD
+    Replace,
+    /// Keep the lone surrogate, the string is then a `Wtf8String`
+    Preserve,
+}
+
+/// A string stored in WTF-8: UTF-8 extended to encode lone surrogates.
+///
+/// The parser only stores strings containing lone surrogates this way, others
+/// are plain `String`s.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Wtf8String {
+    bytes: Vec<u8>,
+}
+
+impl Wtf8String {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        self.bytes.extend_from_slice(s.as_bytes());
+    }
+
+    /// Encode a surrogate as UTF-8 would encode a character of that value. A
+    /// low surrogate following a high one is joined with it in the character
+    /// they stand for.
+    ///
+    /// Panics if `unit` is not a surrogate.
+    pub fn push_surrogate(&mut self, unit: u16) {
+        assert!(
+            (0xD800..=0xDFFF).contains(&unit),
+            "{:#X} is not a surrogate",
+            unit
+        );
+        if let (0xDC00..=0xDFFF, Some(high)) = (unit, self.trailing_high_surrogate()) {
+            self.bytes.truncate(self.bytes.len() - 3);
+            let code_point = 0x10000 + ((high as u32 - 0xD800) << 10) + (unit as u32 - 0xDC00);
+            // A valid pair is a character
+            let c = std::char::from_u32(code_point).unwrap();
+            self.push_str(c.encode_utf8(&mut [0; 4]));
+            return;
+        }
+        self.bytes.extend_from_slice(&[
+            0xE0 | (unit >> 12) as u8,
+            0x80 | ((unit >> 6) & 0x3F) as u8,
+            0x80 | (unit & 0x3F) as u8,
+        ]);
+    }
+
+    fn trailing_high_surrogate(&self) -> Option<u16> {
+        match self.bytes[..] {
+            [.., 0xED, b1 @ 0xA0..=0xAF, b2] => {
+                Some(0xD000 | ((b1 & 0x3F) as u16) << 6 | (b2 & 0x3F) as u16)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// The code points, lone surrogates included.
+    pub fn code_points(&self) -> CodePoints<'_> {
+        CodePoints { bytes: &self.bytes }
+    }
+
+    pub(crate) fn lone_surrogates(&self) -> impl Iterator<Item = u16> + '_ {
+        self.code_points()
+            .filter(|cp| (0xD800..=0xDFFF).contains(cp))
+            .map(|cp| cp as u16)
+    }
+
+    /// The string with each lone surrogate replaced by U+FFFD.
+    pub fn to_string_lossy(&self) -> String {
+        self.code_points()
+            .map(|cp| std::char::from_u32(cp).unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect()
+    }
+}
+
+impl fmt::Display for Wtf8String {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_string_lossy())
+    }
+}
+
+pub struct CodePoints<'a> {
+    bytes: &'a [u8],
+}
+
+impl Iterator for CodePoints<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        let (&lead, rest) = self.bytes.split_first()?;
+        let (width, initial) = match lead {
+            0x00..=0x7F => (1, lead as u32),
+            0xC0..=0xDF => (2, (lead & 0x1F) as u32),
+            0xE0..=0xEF => (3, (lead & 0x0F) as u32),
+            _ => (4, (lead & 0x07) as u32),
+        };
+        let code_point = rest[..width - 1]
+            .iter()
+            .fold(initial, |acc, &b| (acc << 6) | (b & 0x3F) as u32);
+        self.bytes = &rest[width - 1..];
+        Some(code_point)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_points_round_trip() {
+        let mut s = Wtf8String::new();
+        s.push_str("a\u{e9}");
+        s.push_surrogate(0xDC00);
+        s.push_str("\u{1F431}");
+        s.push_surrogate(0xD83D);
+        assert_eq!(
+            s.code_points().collect::<Vec<_>>(),
+            vec![0x61, 0xE9, 0xDC00, 0x1F431, 0xD83D]
+        );
+        assert_eq!(s.len(), 13);
+        assert_eq!(s.to_string_lossy(), "a\u{e9}\u{FFFD}\u{1F431}\u{FFFD}");
+        assert_eq!(&s.as_bytes()[3..6], &[0xED, 0xB0, 0x80]);
+    }
+
+    #[test]
+    fn surrogate_pairs_are_joined() {
+        let mut s = Wtf8String::new();
+        s.push_surrogate(0xD83D);
+        s.push_surrogate(0xDC31);
+        s.push_surrogate(0xDC31);
+        assert_eq!(s.code_points().collect::<Vec<_>>(), vec![0x1F431, 0xDC31]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn push_surrogate_rejects_characters() {
+        Wtf8String::new().push_surrogate(0x41);
+    }
+}
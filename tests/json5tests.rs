@@ -0,0 +1,80 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! The cases of json5-tests, https://github.com/json5/json5-tests, sorted by
+//! their extension: `.json` files are valid JSON and JSON5, `.json5` files
+//! only valid JSON5, and `.js` and `.txt` files invalid JSON5.
+
+use json_parser::{parse_json_with_dialect, Dialect};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn collect(dir: &Path, cases: &mut Vec<(String, String)>) {
+    for entry in fs::read_dir(dir).expect("The fixtures could not be listed") {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect(&path, cases);
+        } else {
+            let name = path.to_string_lossy().into_owned();
+            cases.push((name, fs::read_to_string(&path).unwrap()));
+        }
+    }
+}
+
+fn cases(extensions: &[&str]) -> Vec<(String, String)> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/json5_tests");
+    let mut cases = Vec::new();
+    collect(&dir, &mut cases);
+    cases.retain(|(name, _)| extensions.iter().any(|ext| name.ends_with(ext)));
+    assert!(!cases.is_empty(), "No {:?} fixture", extensions);
+    cases.sort();
+    cases
+}
+
+#[test]
+fn json_cases() {
+    for (name, input) in cases(&[".json"]) {
+        let json = parse_json_with_dialect(&input, Dialect::Json);
+        assert!(json.is_ok(), "{} was rejected as JSON: {:?}", name, json);
+        let json5 = parse_json_with_dialect(&input, Dialect::Json5);
+        assert_eq!(json5, json, "{}", name);
+    }
+}
+
+#[test]
+fn json5_cases() {
+    for (name, input) in cases(&[".json5"]) {
+        if let Err(error) = parse_json_with_dialect(&input, Dialect::Json5) {
+            panic!("{} was rejected: {}", name, error);
+        }
+        let json = parse_json_with_dialect(&input, Dialect::Json);
+        assert!(json.is_err(), "{} was accepted as JSON", name);
+    }
+}
+
+#[test]
+fn rejected_cases() {
+    for (name, input) in cases(&[".js", ".txt"]) {
+        let json5 = parse_json_with_dialect(&input, Dialect::Json5);
+        assert!(json5.is_err(), "{} was accepted", name);
+    }
+}
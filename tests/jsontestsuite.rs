@@ -0,0 +1,137 @@
+/*
+Copyright (c) 2020 Vincent Hiribarren
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+*/
+
+//! The parsing cases of JSONTestSuite, https://github.com/nst/JSONTestSuite:
+//! `y_` files must be accepted, `n_` files rejected, and `i_` files, where
+//! RFC 8259 leaves the choice to implementations, behave as listed below and
+//! in the README.
+
+use json_parser::{parse_json, parse_slice, validate};
+use std::fs;
+use std::path::PathBuf;
+
+/// The implementation defined cases, and whether they are accepted.
+const IMPLEMENTATION_DEFINED: &[(&str, bool)] = &[
+    // Numbers out of range become infinite or zero, but more than 100 digits
+    // exceed the default limit
+    ("i_number_double_huge_neg_exp", true),
+    ("i_number_huge_exp", false),
+    ("i_number_neg_int_huge_exp", true),
+    ("i_number_pos_double_huge_exp", true),
+    ("i_number_real_neg_overflow", true),
+    ("i_number_real_pos_overflow", true),
+    ("i_number_real_underflow", true),
+    ("i_number_too_big_neg_int", true),
+    ("i_number_too_big_pos_int", true),
+    ("i_number_very_big_negative_int", true),
+    // Lone surrogate escapes are errors with the default surrogate policy
+    ("i_object_key_lone_2nd_surrogate", false),
+    ("i_string_1st_surrogate_but_2nd_missing", false),
+    ("i_string_1st_valid_surrogate_2nd_invalid", false),
+    ("i_string_incomplete_surrogate_and_escape_valid", false),
+    ("i_string_incomplete_surrogate_pair", false),
+    ("i_string_incomplete_surrogates_escape_valid", false),
+    ("i_string_invalid_lonely_surrogate", false),
+    ("i_string_invalid_surrogate", false),
+    ("i_string_inverted_surrogates_U+1D11E", false),
+    ("i_string_lone_second_surrogate", false),
+    // Invalid UTF-8 is an error
+    ("i_string_UTF-8_invalid_sequence", false),
+    ("i_string_UTF8_surrogate_U+D800", false),
+    ("i_string_invalid_utf-8", false),
+    ("i_string_iso_latin_1", false),
+    ("i_string_lone_utf8_continuation_byte", false),
+    ("i_string_not_in_unicode_range", false),
+    ("i_string_overlong_sequence_2_bytes", false),
+    ("i_string_overlong_sequence_6_bytes", false),
+    ("i_string_overlong_sequence_6_bytes_null", false),
+    ("i_string_truncated-utf-8", false),
+    // UTF-16 is detected, and a byte order mark skipped
+    ("i_string_UTF-16LE_with_BOM", true),
+    ("i_string_utf16BE_no_BOM", true),
+    ("i_string_utf16LE_no_BOM", true),
+    ("i_structure_UTF-8_BOM_empty_object", true),
+    // Deeper than the default limit of 128
+    ("i_structure_500_nested_arrays", false),
+];
+
+fn fixtures() -> Vec<(String, Vec<u8>)> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/test_parsing");
+    let mut cases: Vec<(String, Vec<u8>)> = fs::read_dir(dir)
+        .expect("The fixtures could not be listed")
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            (name, fs::read(&path).unwrap())
+        })
+        .collect();
+    cases.sort();
+    cases
+}
+
+fn cases(prefix: &str) -> Vec<(String, Vec<u8>)> {
+    let cases: Vec<(String, Vec<u8>)> = fixtures()
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(prefix))
+        .collect();
+    assert!(!cases.is_empty(), "No {} fixture", prefix);
+    cases
+}
+
+#[test]
+fn accepted_cases() {
+    for (name, input) in cases("y_") {
+        if let Err(error) = parse_slice(&input) {
+            panic!("{} was rejected: {}", name, error);
+        }
+    }
+}
+
+#[test]
+fn rejected_cases() {
+    for (name, input) in cases("n_") {
+        assert!(parse_slice(&input).is_err(), "{} was accepted", name);
+    }
+}
+
+#[test]
+fn implementation_defined_cases() {
+    let cases = cases("i_");
+    assert_eq!(cases.len(), IMPLEMENTATION_DEFINED.len());
+    for (name, input) in cases {
+        let accepted = IMPLEMENTATION_DEFINED
+            .iter()
+            .find(|(case, _)| *case == name)
+            .map(|(_, accepted)| *accepted)
+            .unwrap_or_else(|| panic!("{} is not documented", name));
+        assert_eq!(parse_slice(&input).is_ok(), accepted, "{}", name);
+    }
+}
+
+#[test]
+fn validate_agrees_with_parse_json() {
+    for (name, input) in fixtures() {
+        if let Ok(text) = std::str::from_utf8(&input) {
+            assert_eq!(validate(text), parse_json(text).map(|_| ()), "{}", name);
+        }
+    }
+}